@@ -0,0 +1,438 @@
+// Reading BLIF netlists, as written by synthesis tools such as Yosys and ABC
+use std::collections::HashMap;
+
+use crate::{CircuitBuilder, ParseError, WireRef};
+use crate::gates::*;
+use crate::latches::*;
+use crate::memory::*;
+
+// A single `.names` cover
+struct Cover {
+    line: usize,
+    signals: Vec<String>,
+    rows: Vec<Vec<Option<bool>>>,
+    // Whether the rows list the on-set or the off-set, `None` until the first row
+    value: Option<bool>,
+}
+
+struct Latch {
+    line: usize,
+    input: String,
+    output: String,
+    kind: Option<String>,
+    control: Option<String>,
+    initial: bool,
+}
+
+enum Statement {
+    Cover(Cover),
+    Latch(Latch),
+}
+
+#[derive(Default)]
+struct Model {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    clocks: Vec<String>,
+    statements: Vec<Statement>,
+}
+
+// Joins lines continued with a backslash and strips comments, keeping
+// the number of the line each logical line starts on
+fn logical_lines(source: &str) -> Vec<(usize, String)> {
+    let mut result = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (i, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let (line, continued) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let (number, mut text) = pending.take().unwrap_or_else(|| (i + 1, String::new()));
+        text.push(' ');
+        text.push_str(line);
+        if continued {
+            pending = Some((number, text));
+        } else if !text.trim().is_empty() {
+            result.push((number, text));
+        }
+    }
+    result.extend(pending);
+    result
+}
+
+fn parse_row(line: usize, cover: &mut Cover, tokens: &[&str]) -> Result<(), ParseError> {
+    let num_inputs = cover.signals.len() - 1;
+    let (plane, output) = match (num_inputs, tokens) {
+        (0, &[output]) => ("", output),
+        (_, &[plane, output]) if num_inputs > 0 => (plane, output),
+        _ => return Err(ParseError::new(line, "Malformed cover row")),
+    };
+    if plane.len() != num_inputs {
+        return Err(ParseError::new(line, format!("Cover row has {} inputs, expected {}", plane.len(), num_inputs)));
+    }
+    let row = plane.chars().map(|c| match c {
+        '0' => Ok(Some(false)),
+        '1' => Ok(Some(true)),
+        '-' => Ok(None),
+        _ => Err(ParseError::new(line, format!("Unexpected `{}` in cover row", c))),
+    }).collect::<Result<Vec<_>, _>>()?;
+    let value = match output {
+        "0" => false,
+        "1" => true,
+        _ => return Err(ParseError::new(line, format!("Unexpected cover output `{}`", output))),
+    };
+    if cover.value.is_some() && cover.value != Some(value) {
+        return Err(ParseError::new(line, "Cover mixes rows for the on-set and the off-set"));
+    }
+    cover.value = Some(value);
+    cover.rows.push(row);
+    Ok(())
+}
+
+fn parse_latch(line: usize, args: &[&str]) -> Result<Latch, ParseError> {
+    let (input, output, kind, control, initial) = match *args {
+        [input, output] => (input, output, None, None, None),
+        [input, output, initial] => (input, output, None, None, Some(initial)),
+        [input, output, kind, control] => (input, output, Some(kind), Some(control), None),
+        [input, output, kind, control, initial] => (input, output, Some(kind), Some(control), Some(initial)),
+        _ => return Err(ParseError::new(line, "Expected `.latch <input> <output> [<type> <control>] [<init>]`")),
+    };
+    if let Some(kind) = kind {
+        if kind == "as" {
+            return Err(ParseError::new(line, "Asynchronous latches are not supported"));
+        }
+        if !["fe", "re", "ah", "al"].contains(&kind) {
+            return Err(ParseError::new(line, format!("Unknown latch type `{}`", kind)));
+        }
+    }
+    // Initial values of 2 (don't care) and 3 (unknown) start out low
+    let initial = match initial {
+        None | Some("0") | Some("2") | Some("3") => false,
+        Some("1") => true,
+        Some(other) => return Err(ParseError::new(line, format!("Unexpected latch initial value `{}`", other))),
+    };
+    Ok(Latch {
+        line,
+        input: input.into(),
+        output: output.into(),
+        kind: kind.map(String::from),
+        control: control.filter(|&control| control != "NIL").map(String::from),
+        initial,
+    })
+}
+
+fn parse(source: &str) -> Result<Model, ParseError> {
+    let mut model = Model::default();
+    let mut cover: Option<Cover> = None;
+    for (line, text) in logical_lines(source) {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        if !tokens[0].starts_with('.') {
+            match &mut cover {
+                Some(cover) => parse_row(line, cover, &tokens)?,
+                None => return Err(ParseError::new(line, "Cover row outside of `.names`")),
+            }
+            continue;
+        }
+        model.statements.extend(cover.take().map(Statement::Cover));
+        let args = &tokens[1..];
+        match tokens[0] {
+            ".model" => {},
+            ".inputs" => model.inputs.extend(args.iter().map(|&s| String::from(s))),
+            ".outputs" => model.outputs.extend(args.iter().map(|&s| String::from(s))),
+            ".clock" => model.clocks.extend(args.iter().map(|&s| String::from(s))),
+            ".names" => {
+                if args.is_empty() {
+                    return Err(ParseError::new(line, "Expected `.names <inputs> <output>`"));
+                }
+                cover = Some(Cover {
+                    line,
+                    signals: args.iter().map(|&s| String::from(s)).collect(),
+                    rows: Vec::new(),
+                    value: None,
+                });
+            },
+            ".latch" => model.statements.push(Statement::Latch(parse_latch(line, args)?)),
+            // Only the first model is read
+            ".end" => return Ok(model),
+            ".subckt" | ".gate" | ".mlatch" => {
+                return Err(ParseError::new(line, format!("`{}` is not supported, flatten the netlist first", tokens[0])));
+            },
+            other => return Err(ParseError::new(line, format!("Unknown directive `{}`", other))),
+        }
+    }
+    model.statements.extend(cover.take().map(Statement::Cover));
+    Ok(model)
+}
+
+struct Reader {
+    builder: CircuitBuilder,
+    wires: HashMap<String, WireRef>,
+    // The line of the cover or latch driving each signal, or zero for inputs
+    drivers: HashMap<String, usize>,
+    clock: Option<WireRef>,
+}
+
+impl Reader {
+    // Signals are created when they are first mentioned, and named after themselves
+    fn wire(&mut self, name: &str) -> WireRef {
+        let builder = &mut self.builder;
+        *self.wires.entry(name.into()).or_insert_with(|| builder.add_named_wire(name))
+    }
+    fn input(&mut self, name: &str) -> WireRef {
+        let builder = &mut self.builder;
+        self.drivers.insert(name.into(), 0);
+        *self.wires.entry(name.into()).or_insert_with(|| builder.add_named_input_wire(name))
+    }
+    // Each signal can only be driven by a single cover or latch
+    fn drive(&mut self, line: usize, name: &str) -> Result<WireRef, ParseError> {
+        match self.drivers.get(name) {
+            Some(0) => Err(ParseError::new(line, format!("Signal `{}` is an input, and cannot be driven", name))),
+            Some(other) => Err(ParseError::new(line, format!("Signal `{}` is already driven on line {}", name, other))),
+            None => {
+                self.drivers.insert(name.into(), line);
+                Ok(self.wire(name))
+            },
+        }
+    }
+    // Latches without a control signal are clocked by the first `.clock`, or an
+    // input port called `clock` if the model does not declare one
+    fn global_clock(&mut self, line: usize) -> Result<WireRef, ParseError> {
+        if let Some(clock) = self.clock {
+            return Ok(clock);
+        }
+        if self.wires.contains_key("clock") {
+            return Err(ParseError::new(line, "Latch has no clock, and `clock` is already a signal"));
+        }
+        let clock = self.input("clock");
+        self.clock = Some(clock);
+        Ok(clock)
+    }
+    fn add_cover(&mut self, cover: Cover) -> Result<(), ParseError> {
+        let (name, input_names) = cover.signals.split_last().unwrap();
+        let inputs: Vec<WireRef> = input_names.iter().map(|name| self.wire(name)).collect();
+        let inputs = &inputs[..];
+        let output = self.drive(cover.line, name)?;
+        let value = cover.value.unwrap_or(true);
+        let rows = &cover.rows;
+        let n = inputs.len();
+
+        let all_ones = rows.len() == 1 && rows[0].iter().all(|&entry| entry == Some(true));
+        let one_hot = rows.len() == n && rows.iter().enumerate().all(|(i, row)| {
+            row.iter().enumerate().all(|(j, &entry)| entry == if i == j { Some(true) } else { None })
+        });
+        let builder = &mut self.builder;
+        match (n, value) {
+            (0, _) => {
+                let high = rows.is_empty() != value;
+                builder.add_named_component(name, Constant::new(high.into()), &[], &[output])
+            },
+            (1, _) if rows.len() == 1 && rows[0][0].is_some() => {
+                if rows[0][0] == Some(value) {
+                    builder.add_named_component(name, Buffer::new(), inputs, &[output])
+                } else {
+                    builder.add_named_component(name, NotGate::new(), inputs, &[output])
+                }
+            },
+            (2, true) if all_ones => builder.add_named_component(name, AndGate::new(), inputs, &[output]),
+            (2, false) if all_ones => builder.add_named_component(name, NandGate::new(), inputs, &[output]),
+            (_, true) if all_ones => builder.add_named_component(name, NaryAndGate::new(), inputs, &[output]),
+            (2, true) if one_hot => builder.add_named_component(name, OrGate::new(), inputs, &[output]),
+            (2, false) if one_hot => builder.add_named_component(name, NorGate::new(), inputs, &[output]),
+            (_, true) if one_hot => builder.add_named_component(name, NaryOrGate::new(), inputs, &[output]),
+            _ => builder.add_named_component(name, LookupTable::new(n, cover.rows, value), inputs, &[output]),
+        };
+        Ok(())
+    }
+    fn add_latch(&mut self, latch: Latch) -> Result<(), ParseError> {
+        let input = self.wire(&latch.input);
+        let output = self.drive(latch.line, &latch.output)?;
+        let control = match (&latch.kind, &latch.control) {
+            (Some(_), Some(control)) => self.wire(control),
+            _ => self.global_clock(latch.line)?,
+        };
+        let name = &latch.output;
+        let inputs = [input, control];
+        match latch.kind.as_ref().map(|kind| &kind[..]) {
+            Some(kind @ "ah") | Some(kind @ "al") => {
+                let mut component = DLatch::new();
+                component.set_active_low(kind == "al").set_initial(latch.initial);
+                self.builder.add_named_component(name, component, &inputs, &[output])
+            },
+            kind => {
+                let mut component = DFlipFlop::new();
+                component.set_falling_edge(kind == Some("fe")).set_initial(latch.initial);
+                self.builder.add_named_component(name, component, &inputs, &[output])
+            },
+        };
+        Ok(())
+    }
+}
+
+// Builds a circuit from the first model in a BLIF file. Each signal becomes a wire
+// with the same name, and each cover or latch a component named after its output.
+// Model inputs and clocks become input ports. Covers are mapped onto simple gates
+// where possible, and onto a `LookupTable` otherwise. Latches become `DFlipFlop`s,
+// or `DLatch`es for the level sensitive types. Asynchronous latches are not supported.
+pub fn read_blif(source: &str) -> Result<CircuitBuilder, ParseError> {
+    let model = parse(source)?;
+    let mut reader = Reader {
+        builder: CircuitBuilder::new(),
+        wires: HashMap::new(),
+        drivers: HashMap::new(),
+        clock: None,
+    };
+    for name in model.inputs.iter().chain(&model.clocks) {
+        reader.input(name);
+    }
+    if let Some(clock) = model.clocks.first() {
+        reader.clock = Some(reader.wires[clock]);
+    }
+    for name in &model.outputs {
+        reader.wire(name);
+    }
+    for statement in model.statements {
+        match statement {
+            Statement::Cover(cover) => reader.add_cover(cover)?,
+            Statement::Latch(latch) => reader.add_latch(latch)?,
+        }
+    }
+    Ok(reader.builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Voltage;
+
+    #[test]
+    fn toggle() {
+        let builder = read_blif(r"
+# A flip-flop which toggles when enabled
+.model toggle
+.inputs en clk
+.outputs q \
+    both
+.names en q d
+01 1
+10 1
+.names en q both
+11 1
+.latch d q re clk 1
+.end
+").unwrap();
+        assert!(builder.lint().is_empty());
+        let en = builder.wire_by_name("en").unwrap();
+        let clk = builder.wire_by_name("clk").unwrap();
+        let q = builder.wire_by_name("q").unwrap();
+        let both = builder.wire_by_name("both").unwrap();
+        let and = builder.component_by_name("both").unwrap();
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(q).voltage, Voltage::High);
+        // Panics unless the cover was mapped onto a gate
+        circuit.component_ref::<AndGate>(and);
+
+        for &(enabled, expected) in &[(true, Voltage::Low), (false, Voltage::Low), (true, Voltage::High)] {
+            circuit.set_input(en, enabled.into());
+            assert!(circuit.propagate(10).is_stable());
+            circuit.set_input(clk, Voltage::High.into());
+            assert!(circuit.propagate(10).is_stable());
+            assert_eq!(circuit.wire(q).voltage, expected);
+            circuit.set_input(clk, Voltage::Low.into());
+            assert!(circuit.propagate(10).is_stable());
+        }
+        assert_eq!(circuit.wire(both).voltage, Voltage::High);
+
+        assert_eq!(
+            read_blif(".model bad\n.names a b c\n1x 1\n").err().map(|e| e.to_string()),
+            Some("Line 3: Unexpected `x` in cover row".into())
+        );
+        assert_eq!(
+            read_blif(".model bad\n.inputs a\n.names a b\n1 1\n.latch a b\n").err().map(|e| e.to_string()),
+            Some("Line 5: Signal `b` is already driven on line 3".into())
+        );
+        assert_eq!(
+            read_blif(".model bad\n.inputs a\n.names a\n1\n").err().map(|e| e.to_string()),
+            Some("Line 3: Signal `a` is an input, and cannot be driven".into())
+        );
+        assert_eq!(
+            read_blif(".model bad\n.inputs a\n.names a b\n1 1\n.names a b\n0 1\n").err().map(|e| e.to_string()),
+            Some("Line 5: Signal `b` is already driven on line 3".into())
+        );
+    }
+
+    #[test]
+    fn latch_types() {
+        let builder = read_blif(r"
+.model latches
+.inputs d c
+.outputs f h l
+.latch d f fe c 0
+.latch d h ah c
+.latch d l al c 1
+.end
+").unwrap();
+        let d = builder.wire_by_name("d").unwrap();
+        let c = builder.wire_by_name("c").unwrap();
+        let outputs = ["f", "h", "l"].iter().map(|name| builder.wire_by_name(name).unwrap()).collect::<Vec<_>>();
+        let mut circuit = builder.build();
+
+        use crate::Voltage::*;
+        for &(input, control, expected) in &[
+            (High, Low, [Low, Low, High]),
+            (High, High, [Low, High, High]),
+            (Low, High, [Low, Low, High]),
+            (High, High, [Low, High, High]),
+            (High, Low, [High, High, High]),
+            (Low, Low, [High, High, Low]),
+        ] {
+            circuit.set_input(d, input.into());
+            circuit.set_input(c, control.into());
+            assert!(circuit.propagate(10).is_stable());
+            assert_eq!(circuit.bus(&outputs).voltages.to_vec(), expected.to_vec(), "d: {:?}, c: {:?}", input, control);
+        }
+
+        assert_eq!(
+            read_blif(".model bad\n.inputs d c\n.latch d q as c\n").err().map(|e| e.to_string()),
+            Some("Line 3: Asynchronous latches are not supported".into())
+        );
+    }
+
+    #[test]
+    fn off_set() {
+        let builder = read_blif(r"
+.model covers
+.inputs a b c
+.outputs nand even zero
+.names a b nand
+11 0
+.names a b c even
+001 0
+010 0
+100 0
+111 0
+.names zero
+0
+.end
+").unwrap();
+        let inputs = ["a", "b", "c"].iter().map(|name| builder.wire_by_name(name).unwrap()).collect::<Vec<_>>();
+        let nand = builder.wire_by_name("nand").unwrap();
+        let even = builder.wire_by_name("even").unwrap();
+        let zero = builder.wire_by_name("zero").unwrap();
+        let gate = builder.component_by_name("nand").unwrap();
+        let mut circuit = builder.build();
+        circuit.component_ref::<NandGate>(gate);
+
+        for value in 0..8u32 {
+            for (i, &input) in inputs.iter().enumerate() {
+                circuit.set_input(input, (value & (1 << i) != 0).into());
+            }
+            assert!(circuit.propagate(10).is_stable());
+            assert_eq!(circuit.wire(nand).voltage, (value & 3 != 3).into(), "value: {}", value);
+            assert_eq!(circuit.wire(even).voltage, (value.count_ones() % 2 == 0).into(), "value: {}", value);
+            assert_eq!(circuit.wire(zero).voltage, Voltage::Low);
+        }
+    }
+}
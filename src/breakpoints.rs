@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use smallvec::SmallVec;
+
+use crate::{BusRef, Circuit, PropagateResult, Voltage, WireChange, WireRef};
+use crate::circuit::WATCH_BREAK;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    WireEquals(WireRef, Voltage),
+    BusEquals(BusRef, u64),
+    // The wire goes to `Voltage::Error`
+    WireError(WireRef),
+    // The wire goes from low to high
+    RisingEdge(WireRef),
+}
+
+impl Breakpoint {
+    fn wires(&self) -> &[WireRef] {
+        match self {
+            Breakpoint::WireEquals(wire, _) | Breakpoint::WireError(wire) | Breakpoint::RisingEdge(wire) => {
+                std::slice::from_ref(wire)
+            },
+            Breakpoint::BusEquals(bus, _) => bus,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BreakpointRef(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BreakpointHit {
+    pub breakpoint: BreakpointRef,
+    pub tick: u64,
+    pub iteration: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Breakpoints {
+    breakpoints: Vec<Option<Breakpoint>>,
+    by_wire: HashMap<usize, SmallVec<[usize; 2]>>,
+    // Bus breakpoints can only be checked once every wire has been updated
+    pending: Vec<usize>,
+    pub(crate) hit: Option<BreakpointHit>,
+}
+
+impl Breakpoints {
+    pub(crate) fn notify(&mut self, change: &WireChange) {
+        if let Some(ids) = self.by_wire.get(&change.wire.0) {
+            for &id in ids {
+                let fired = match self.breakpoints[id] {
+                    Some(Breakpoint::WireEquals(_, voltage)) => change.new == voltage,
+                    Some(Breakpoint::WireError(_)) => change.new == Voltage::Error,
+                    Some(Breakpoint::RisingEdge(_)) => change.old == Voltage::Low && change.new == Voltage::High,
+                    Some(Breakpoint::BusEquals(..)) => {
+                        if !self.pending.contains(&id) {
+                            self.pending.push(id);
+                        }
+                        false
+                    },
+                    None => false,
+                };
+                if fired && self.hit.is_none() {
+                    self.hit = Some(BreakpointHit {
+                        breakpoint: BreakpointRef(id),
+                        tick: change.tick,
+                        iteration: change.iteration,
+                    });
+                }
+            }
+        }
+    }
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Predicate,
+    Breakpoint(BreakpointRef),
+    // The circuit failed to settle within the iteration limit
+    Unstable(PropagateResult),
+    MaxTicks,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunStop {
+    pub reason: StopReason,
+    pub tick: u64,
+    pub iteration: usize,
+}
+
+impl Circuit {
+    // Breakpoints stop `propagate` at the end of the iteration in which they fire,
+    // leaving any remaining changes to be propagated by the next call. Unconnected
+    // wires are always floating, so a bus containing one never equals a value.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> BreakpointRef {
+        let id = self.breakpoints.breakpoints.len();
+        for wire in breakpoint.wires().iter().filter(|&&wire| wire != WireRef::NONE) {
+            let ids = self.breakpoints.by_wire.entry(wire.0).or_default();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+            self.wires[wire.0].watch |= WATCH_BREAK;
+        }
+        self.breakpoints.breakpoints.push(Some(breakpoint));
+        BreakpointRef(id)
+    }
+    pub fn remove_breakpoint(&mut self, breakpoint: BreakpointRef) {
+        self.breakpoints.breakpoints[breakpoint.0] = None;
+        let wires = &mut self.wires;
+        self.breakpoints.by_wire.retain(|&wire_id, ids| {
+            ids.retain(|id| *id != breakpoint.0);
+            if ids.is_empty() {
+                wires[wire_id].watch &= !WATCH_BREAK;
+            }
+            !ids.is_empty()
+        });
+    }
+    pub fn breakpoint(&self, breakpoint: BreakpointRef) -> Option<&Breakpoint> {
+        self.breakpoints.breakpoints[breakpoint.0].as_ref()
+    }
+    pub(crate) fn check_pending_breakpoints(&mut self) {
+        for id in std::mem::take(&mut self.breakpoints.pending) {
+            if let Some(Breakpoint::BusEquals(bus, value)) = &self.breakpoints.breakpoints[id] {
+                let connected = bus.iter().all(|&wire| wire != WireRef::NONE);
+                if connected && self.bus(bus).to_u64() == Some(*value) && self.breakpoints.hit.is_none() {
+                    self.breakpoints.hit = Some(BreakpointHit {
+                        breakpoint: BreakpointRef(id),
+                        tick: self.change_tick(),
+                        iteration: self.iteration_count,
+                    });
+                }
+            }
+        }
+    }
+    pub(crate) fn paused_result(&mut self, iterations: usize) -> PropagateResult {
+        if self.history.is_some() {
+            self.record_pause(iterations);
+        }
+        self.pending_result(iterations)
+    }
+    fn stop(&self, reason: StopReason) -> RunStop {
+        RunStop {
+            reason,
+            tick: self.change_tick(),
+            iteration: self.iteration_count,
+        }
+    }
+    // Finishes propagating any outstanding changes, then repeatedly ticks and
+    // propagates the circuit until `predicate` returns true, a breakpoint fires,
+    // the circuit fails to settle, or `max_ticks` ticks have elapsed.
+    pub fn run_until<F: FnMut(&Circuit) -> bool>(&mut self, predicate: F, max_ticks: u64, max_iterations: usize) -> RunStop {
+        self.run_ticks(predicate, max_ticks, max_iterations, |_, _, _| {})
+    }
+    // The loop behind `run_until`, which calls `settled` with the number of ticks
+    // run so far each time the circuit settles
+    pub(crate) fn run_ticks<F, G>(&mut self, mut predicate: F, max_ticks: u64, max_iterations: usize, mut settled: G) -> RunStop
+    where
+        F: FnMut(&Circuit) -> bool,
+        G: FnMut(&Circuit, u64, &PropagateResult),
+    {
+        let mut ticks = 0;
+        loop {
+            // Ticks which don't change anything leave nothing to propagate
+            let result = if !self.is_stable() || !self.dirty_components.is_empty() {
+                self.propagate(max_iterations)
+            } else {
+                PropagateResult::stable(0)
+            };
+            if let Some(hit) = result.breakpoint {
+                return RunStop {
+                    reason: StopReason::Breakpoint(hit.breakpoint),
+                    tick: hit.tick,
+                    iteration: hit.iteration,
+                };
+            }
+            if !result.is_stable() {
+                return self.stop(StopReason::Unstable(result));
+            }
+            settled(self, ticks, &result);
+            if ticks > 0 && predicate(self) {
+                return self.stop(StopReason::Predicate);
+            }
+            if ticks == max_ticks {
+                return self.stop(StopReason::MaxTicks);
+            }
+            self.tick();
+            ticks += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CircuitBuilder;
+    use crate::arithmetic::*;
+    use crate::clocks::*;
+
+    #[test]
+    fn run_until() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_wire();
+        let a = builder.add_input_bus(4);
+        let b = builder.add_input_bus(4);
+        let cin = builder.add_input_wire();
+        let sum = builder.add_bus(5);
+        builder.add_component(Clock::new(), &[], &[clk]);
+        builder.add_component(Adder::new(), &a.concat(&b).concat(&[cin]), &sum);
+        let mut circuit = builder.build();
+
+        let stop = circuit.run_until(|circuit| circuit.wire(clk).voltage == Voltage::High, 10, 10);
+        assert_eq!((stop.reason, stop.tick), (StopReason::Predicate, 1));
+
+        let edge = circuit.add_breakpoint(Breakpoint::RisingEdge(clk));
+        let stop = circuit.run_until(|_| false, 10, 10);
+        assert_eq!((stop.reason, stop.tick, stop.iteration), (StopReason::Breakpoint(edge), 3, 0));
+        circuit.remove_breakpoint(edge);
+        assert_eq!(circuit.breakpoint(edge), None);
+
+        // Outstanding changes are propagated before the next tick
+        let five = circuit.add_breakpoint(Breakpoint::BusEquals(sum.clone(), 5));
+        circuit.set_input_bus(&a, 2);
+        circuit.set_input_bus(&b, 3);
+        let stop = circuit.run_until(|_| false, 10, 10);
+        assert_eq!(stop.reason, StopReason::Breakpoint(five));
+        assert_eq!(stop.tick, 3);
+        assert!(stop.iteration > 0);
+        assert_eq!(circuit.bus(&sum).to_u64(), Some(5));
+        circuit.remove_breakpoint(five);
+
+        // Unconnected bits never match
+        let low = BusRef::from(&[sum[0], WireRef::NONE][..]);
+        circuit.add_breakpoint(Breakpoint::BusEquals(low, 0));
+        circuit.set_input_bus(&a, 1);
+
+        let stop = circuit.run_until(|_| false, 3, 10);
+        assert_eq!((stop.reason, stop.tick), (StopReason::MaxTicks, 6));
+    }
+}
@@ -0,0 +1,123 @@
+use std::iter::FromIterator;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::slice;
+
+use smallvec::SmallVec;
+
+use crate::{Voltage, WireRef};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BusRef(SmallVec<[WireRef; 8]>);
+
+impl BusRef {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn width(&self) -> usize {
+        self.0.len()
+    }
+    pub fn wires(&self) -> &[WireRef] {
+        &self.0
+    }
+    pub fn bit(&self, index: usize) -> WireRef {
+        self.0[index]
+    }
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BusRef {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.0.len(),
+        };
+        self.0[start..end].into()
+    }
+    pub fn concat(&self, other: &[WireRef]) -> BusRef {
+        let mut result = self.clone();
+        result.0.extend_from_slice(other);
+        result
+    }
+    pub fn push(&mut self, wire: WireRef) -> &mut Self {
+        self.0.push(wire);
+        self
+    }
+}
+
+impl Deref for BusRef {
+    type Target = [WireRef];
+    fn deref(&self) -> &[WireRef] {
+        &self.0
+    }
+}
+
+impl AsRef<[WireRef]> for BusRef {
+    fn as_ref(&self) -> &[WireRef] {
+        &self.0
+    }
+}
+
+impl From<WireRef> for BusRef {
+    fn from(wire: WireRef) -> Self {
+        BusRef(SmallVec::from_slice(&[wire]))
+    }
+}
+
+impl<'a> From<&'a [WireRef]> for BusRef {
+    fn from(wires: &'a [WireRef]) -> Self {
+        BusRef(SmallVec::from_slice(wires))
+    }
+}
+
+impl FromIterator<WireRef> for BusRef {
+    fn from_iter<I: IntoIterator<Item = WireRef>>(iter: I) -> Self {
+        BusRef(iter.into_iter().collect())
+    }
+}
+
+impl Extend<WireRef> for BusRef {
+    fn extend<I: IntoIterator<Item = WireRef>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl<'a> IntoIterator for &'a BusRef {
+    type Item = &'a WireRef;
+    type IntoIter = slice::Iter<'a, WireRef>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BusValue {
+    pub voltages: SmallVec<[Voltage; 8]>,
+    pub unstable: bool,
+}
+
+impl BusValue {
+    pub fn width(&self) -> usize {
+        self.voltages.len()
+    }
+    pub fn bit(&self, index: usize) -> Voltage {
+        self.voltages[index]
+    }
+    // Returns `None` if any bit is floating or in error, or if the bus is too wide
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.voltages.len() > 64 {
+            return None;
+        }
+        let mut result = 0;
+        for (i, &voltage) in self.voltages.iter().enumerate() {
+            match voltage {
+                Voltage::Low => {},
+                Voltage::High => { result |= 1 << i; },
+                _ => return None,
+            }
+        }
+        Some(result)
+    }
+}
@@ -1,11 +1,23 @@
 use std::collections::HashMap;
-use std::any::Any;
+use std::any::{self, Any};
 use std::fmt;
+use std::sync::Arc;
 
 use smallvec::{SmallVec, smallvec};
 
+use crate::{BusRef, BusValue, Arity, BuildError, Description, WireChange, PropagateResult, StateReader, StateWriter};
+use crate::diagnostics::QueueStates;
+use crate::drivers::ErrorOrigins;
+use crate::history::History;
+use crate::breakpoints::Breakpoints;
+use crate::names::Names;
+use crate::observe::Observers;
+use crate::schedule::{NEVER, WakeList};
+use crate::pins::pin_labels;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Voltage {
     Floating = 0,
     Low = 1,
@@ -26,6 +38,7 @@ impl From<u8> for Voltage {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VoltageInput {
     pub voltage: Voltage,
     pub resistor: bool,
@@ -57,30 +70,56 @@ impl From<bool> for Voltage {
 
 pub trait Component: fmt::Debug + Any {
     fn update(&mut self, interface: &mut ComponentInterface);
+    // Called on each tick for which the component is scheduled by `next_tick`,
+    // returns true if the component should then be updated.
     fn tick(&mut self, _tick: u64) -> bool { false }
-
+    // The first tick at or after `tick` at which the component needs to be ticked,
+    // or `None` if it never does. This is asked when the circuit is built, after each
+    // call to `tick`, after the component is modified via `component_mut`, and after
+    // an update in which the component called `ComponentInterface::reschedule`.
+    // By default components are ticked every tick, components which never need
+    // ticking should return `None` so that they can be skipped.
+    fn next_tick(&self, tick: u64) -> Option<u64> { Some(tick) }
+    // Sequential components hold state, so feedback loops passing through
+    // them are expected, and are not reported as combinational loops.
+    fn is_sequential(&self) -> bool { false }
+    fn describe(&self) -> Option<Description> { None }
+    // Derived from the pin layout, for components which describe themselves
+    fn arity(&self) -> Option<Arity> { self.describe().map(|description| description.arity()) }
+    // Components with internal state must save and load it for snapshots to be accurate
+    fn save_state(&self, _state: &mut StateWriter) {}
+    fn load_state(&mut self, _state: &mut StateReader) {}
 }
 
 pub trait AnyComponent: Component {
-    fn as_any_mut(&mut self) -> &mut Any;
-    fn as_any_ref(&self) -> &Any;
-    fn clone_box(&self) -> Box<AnyComponent>;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn as_any_ref(&self) -> &dyn Any;
+    fn clone_box(&self) -> Box<dyn AnyComponent>;
 }
 
 impl<C: Component + Clone> AnyComponent for C {
-    fn as_any_mut(&mut self) -> &mut Any { self }
-    fn as_any_ref(&self) -> &Any { self }
-    fn clone_box(&self) -> Box<AnyComponent> { Box::new(self.clone()) }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn as_any_ref(&self) -> &dyn Any { self }
+    fn clone_box(&self) -> Box<dyn AnyComponent> { Box::new(self.clone()) }
 }
 
 #[derive(Debug, Clone)]
-struct Wire {
-    voltage: Voltage,
-    inputs: SmallVec<[VoltageInput; 2]>,
-    invalidation_id: usize,
-    next: usize,
+pub(crate) struct Wire {
+    pub(crate) voltage: Voltage,
+    pub(crate) inputs: SmallVec<[VoltageInput; 2]>,
+    pub(crate) invalidation_id: usize,
+    pub(crate) next: usize,
+    pub(crate) watch: u8,
+    // Input ports reserve the first input slot for the host to drive
+    pub(crate) external: bool,
 }
 
+// Reasons a wire may be watched for changes
+pub(crate) const WATCH_TRACE: u8 = 1;
+pub(crate) const WATCH_OBSERVE: u8 = 2;
+pub(crate) const WATCH_BREAK: u8 = 4;
+pub(crate) const WATCH_ERROR: u8 = 8;
+
 impl Wire {
     fn state(&self) -> WireState {
         WireState {
@@ -97,9 +136,9 @@ pub struct WireState {
 }
 
 #[derive(Debug, Copy, Clone)]
-struct Pin {
-    wire_id: usize,
-    input_id: usize,
+pub(crate) struct Pin {
+    pub(crate) wire_id: usize,
+    pub(crate) input_id: usize,
 }
 
 #[derive(Debug)]
@@ -108,10 +147,11 @@ pub struct ComponentInterface<'a> {
     wires: &'a mut [Wire],
     inputs: &'a [usize],
     outputs: &'a [Pin],
+    reschedule: bool,
 }
 
-const NULL_INDEX: usize = !0;
-const TAIL_INDEX: usize = !0-1;
+pub(crate) const NULL_INDEX: usize = !0;
+pub(crate) const TAIL_INDEX: usize = !0-1;
 
 impl<'a> ComponentInterface<'a> {
     pub fn input(&self, index: usize) -> Voltage {
@@ -130,6 +170,10 @@ impl<'a> ComponentInterface<'a> {
             }
         }
     }
+    // Asks the circuit to call `next_tick` again once this update is finished
+    pub fn reschedule(&mut self) {
+        self.reschedule = true;
+    }
     pub fn num_inputs(&self) -> usize {
         self.inputs.len()
     }
@@ -139,17 +183,19 @@ impl<'a> ComponentInterface<'a> {
 }
 
 #[derive(Debug)]
-struct ComponentWrapper {
-    iteration: usize,
-    inputs: SmallVec<[usize; 4]>,
-    outputs: SmallVec<[Pin; 2]>,
-    component_impl: Box<AnyComponent>,
+pub(crate) struct ComponentWrapper {
+    pub(crate) iteration: usize,
+    pub(crate) next_tick: u64,
+    pub(crate) inputs: SmallVec<[usize; 4]>,
+    pub(crate) outputs: SmallVec<[Pin; 2]>,
+    pub(crate) component_impl: Box<dyn AnyComponent>,
 }
 
 impl Clone for ComponentWrapper {
     fn clone(&self) -> Self {
         ComponentWrapper {
             iteration: self.iteration,
+            next_tick: self.next_tick,
             inputs: self.inputs.clone(),
             outputs: self.outputs.clone(),
             component_impl: self.component_impl.clone_box(),
@@ -158,20 +204,38 @@ impl Clone for ComponentWrapper {
 }
 
 #[derive(Clone, Debug)]
-struct ComponentSet {
-    components: SmallVec<[usize; 4]>,
-    next: usize,
+pub(crate) struct ComponentSet {
+    pub(crate) components: SmallVec<[usize; 4]>,
+    pub(crate) next: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Circuit {
-    iteration_count: usize,
-    tick_count: u64,
-    first_component_set: usize,
-    first_wire: usize,
-    wires: Vec<Wire>,
-    component_sets: Vec<ComponentSet>,
-    components: Vec<ComponentWrapper>,
+    pub(crate) iteration_count: usize,
+    // Iterations performed before the current tick, so that components
+    // can be stamped with the iteration in which they were last updated
+    pub(crate) iteration_base: usize,
+    pub(crate) tick_count: u64,
+    pub(crate) first_component_set: usize,
+    pub(crate) first_wire: usize,
+    pub(crate) wires: Vec<Wire>,
+    pub(crate) component_sets: Vec<ComponentSet>,
+    pub(crate) components: Vec<ComponentWrapper>,
+    pub(crate) names: Arc<Names>,
+    pub(crate) changes: Vec<WireChange>,
+    pub(crate) observers: Observers,
+    pub(crate) oscillation_detection: bool,
+    pub(crate) history: Option<History>,
+    // Components modified by the host, which must be updated by the next `propagate`
+    pub(crate) dirty_components: Vec<usize>,
+    pub(crate) breakpoints: Breakpoints,
+    // Running totals, for statistics
+    pub(crate) components_updated: u64,
+    pub(crate) wires_changed: u64,
+    pub(crate) wake_list: WakeList,
+    // Components which must be asked for their next tick before the next `tick`
+    pub(crate) rescheduled: Vec<usize>,
+    pub(crate) error_origins: Option<ErrorOrigins>,
 }
 
 impl Circuit {
@@ -195,8 +259,14 @@ impl Circuit {
             }
         }
 
+        let old_voltage = wire.voltage;
+        let watch = wire.watch;
+        let result = wire.next;
+        wire.next = NULL_INDEX;
+
         // Invalidate components
-        if wire.voltage != new_voltage {
+        if old_voltage != new_voltage {
+            self.wires_changed += 1;
             wire.voltage = new_voltage;
             if wire.invalidation_id != NULL_INDEX {
                 let component_set = &mut self.component_sets[wire.invalidation_id];
@@ -205,12 +275,35 @@ impl Circuit {
                     self.first_component_set = wire.invalidation_id;
                 }
             }
+            if watch != 0 {
+                self.wire_changed(wire_id, watch, old_voltage, new_voltage);
+            }
         }
 
-        let result = wire.next;
-        wire.next = NULL_INDEX;
         result
     }
+    #[cold]
+    fn wire_changed(&mut self, wire_id: usize, watch: u8, old: Voltage, new: Voltage) {
+        let change = WireChange {
+            wire: WireRef(wire_id),
+            old,
+            new,
+            tick: self.change_tick(),
+            iteration: self.iteration_count,
+        };
+        if watch & WATCH_TRACE != 0 {
+            self.changes.push(change);
+        }
+        if watch & WATCH_OBSERVE != 0 {
+            self.observers.notify(&change);
+        }
+        if watch & WATCH_BREAK != 0 {
+            self.breakpoints.notify(&change);
+        }
+        if watch & WATCH_ERROR != 0 && new == Voltage::Error {
+            self.record_error_origin(wire_id);
+        }
+    }
     fn update_wires(&mut self) {
         while self.first_wire != TAIL_INDEX {
             self.first_wire = self.update_wire(self.first_wire);
@@ -226,48 +319,128 @@ impl Circuit {
                     continue;
                 }
                 component.iteration = iteration;
+                self.components_updated += 1;
                 let mut interface = ComponentInterface {
                     first_wire: &mut self.first_wire,
                     wires: &mut self.wires,
                     inputs: &component.inputs,
                     outputs: &component.outputs,
+                    reschedule: false,
                 };
                 component.component_impl.update(&mut interface);
+                if interface.reschedule {
+                    self.wake_list.schedule(component_id, component, self.tick_count);
+                }
             }
 
             self.first_component_set = component_set.next;
             component_set.next = NULL_INDEX;
         }
     }
-    pub fn propagate(&mut self, max_iterations: usize) -> bool {
+    fn update_dirty_components(&mut self) {
+        let mut dirty = std::mem::take(&mut self.dirty_components);
+        self.components_updated += dirty.len() as u64;
+        for &component_id in &dirty {
+            let component = &mut self.components[component_id];
+            let mut interface = ComponentInterface {
+                first_wire: &mut self.first_wire,
+                wires: &mut self.wires,
+                inputs: &component.inputs,
+                outputs: &component.outputs,
+                reschedule: false,
+            };
+            component.component_impl.update(&mut interface);
+            if interface.reschedule {
+                self.wake_list.schedule(component_id, component, self.tick_count);
+            }
+        }
+        dirty.clear();
+        self.dirty_components = dirty;
+    }
+    pub(crate) fn is_stable(&self) -> bool {
+        self.first_wire == TAIL_INDEX && self.first_component_set == TAIL_INDEX
+    }
+    pub fn propagate(&mut self, max_iterations: usize) -> PropagateResult {
+        self.propagate_impl(max_iterations, true)
+    }
+    // Performs a single iteration of `propagate`, so that the signals can be followed
+    // through the circuit using `pending_wires` and `pending_components`.
+    pub fn step_iteration(&mut self) -> PropagateResult {
+        self.propagate_impl(1, false)
+    }
+    fn propagate_impl(&mut self, max_iterations: usize, diagnose: bool) -> PropagateResult {
+        if self.history.is_some() {
+            self.record_propagate(max_iterations);
+        }
+        if !self.dirty_components.is_empty() {
+            self.update_dirty_components();
+        }
+        let first_iteration = self.iteration_count;
+        let mut seen = QueueStates::new();
+
         // Propagate changes
-        for _ in 0..max_iterations {
+        for i in 0..max_iterations {
             self.update_wires();
-            self.update_components(self.iteration_count);
+            if self.breakpoints.has_pending() {
+                self.check_pending_breakpoints();
+            }
+            self.update_components(self.iteration_base + self.iteration_count);
             self.iteration_count += 1;
 
             // No more changes to propagate
-            if self.first_wire == TAIL_INDEX && self.first_component_set == TAIL_INDEX {
-                return true;
+            if self.is_stable() {
+                let mut result = PropagateResult::stable(i + 1);
+                result.breakpoint = self.breakpoints.hit.take();
+                return result;
+            }
+            if self.breakpoints.hit.is_some() {
+                return self.paused_result(i + 1);
+            }
+
+            // Give up early if the circuit is going round in circles
+            if self.oscillation_detection {
+                if let Some(oscillation) = self.check_oscillation(&mut seen) {
+                    let mut result = self.unstable_result(first_iteration);
+                    result.oscillation = Some(oscillation);
+                    return result;
+                }
             }
         }
 
-        false
+        if self.is_stable() {
+            PropagateResult::stable(0)
+        } else if diagnose {
+            self.unstable_result(first_iteration)
+        } else {
+            self.pending_result(max_iterations)
+        }
     }
     pub fn tick(&mut self) {
-        // Tick all the components
+        if self.history.is_some() {
+            self.record_tick();
+        }
+
+        if !self.rescheduled.is_empty() {
+            self.flush_rescheduled();
+        }
+        self.iteration_base += self.iteration_count;
         self.iteration_count = 0;
-        for component in &mut self.components {
-            component.iteration = NULL_INDEX;
+
+        // Tick the components which are due
+        while let Some(component_id) = self.wake_list.pop_due(&mut self.components, self.tick_count) {
+            let component = &mut self.components[component_id];
             if component.component_impl.tick(self.tick_count) {
+                self.components_updated += 1;
                 let mut interface = ComponentInterface {
                     first_wire: &mut self.first_wire,
                     wires: &mut self.wires,
                     inputs: &component.inputs,
                     outputs: &component.outputs,
+                    reschedule: false,
                 };
                 component.component_impl.update(&mut interface);
             }
+            self.wake_list.schedule(component_id, component, self.tick_count + 1);
         }
         self.tick_count += 1;
     }
@@ -280,35 +453,168 @@ impl Circuit {
                 wires: &mut self.wires,
                 inputs: &component.inputs,
                 outputs: &component.outputs,
+                reschedule: false,
             };
             component.component_impl.update(&mut interface);
         }
+        self.schedule_all();
+    }
+    fn check_component_type<C: Component>(&self, cref: ComponentRef) {
+        let component_impl = &self.components[cref.0].component_impl;
+        if !component_impl.as_any_ref().is::<C>() {
+            panic!(
+                "Component {} is {:?}, not {}",
+                self.names.component_label(cref), component_impl, any::type_name::<C>()
+            );
+        }
     }
     pub fn component_mut<C: Component>(&mut self, cref: ComponentRef) -> &mut C {
+        self.check_component_type::<C>(cref);
+        if let Some(history) = &mut self.history {
+            history.touch(cref.0);
+        }
+        self.reschedule(cref.0);
         self.components[cref.0].component_impl.as_any_mut().downcast_mut().unwrap()
     }
+    // Unlike `component_mut`, the component is updated by the next call to `propagate`,
+    // so changes take effect without waiting for the next tick.
+    pub fn modify_component<C: Component, R, F: FnOnce(&mut C) -> R>(&mut self, cref: ComponentRef, f: F) -> R {
+        let result = f(self.component_mut::<C>(cref));
+        if !self.dirty_components.contains(&cref.0) {
+            self.dirty_components.push(cref.0);
+        }
+        result
+    }
     pub fn component_ref<C: Component>(&self, cref: ComponentRef) -> &C {
+        self.check_component_type::<C>(cref);
         self.components[cref.0].component_impl.as_any_ref().downcast_ref().unwrap()
     }
+    // Drives an input port, the new value is seen by the next call to `propagate`
+    pub fn set_input(&mut self, wref: WireRef, voltage: VoltageInput) {
+        let wire = &mut self.wires[wref.0];
+        if !wire.external {
+            panic!("Wire {} is not an input port", self.names.wire_label(wref));
+        }
+        if wire.inputs[0] != voltage {
+            wire.inputs[0] = voltage;
+            if wire.next == NULL_INDEX {
+                wire.next = self.first_wire;
+                self.first_wire = wref.0;
+            }
+            if self.history.is_some() {
+                self.record_input(wref, voltage);
+            }
+        }
+    }
+    // Drives a bus of input ports with the bits of `value`, least significant bit first
+    pub fn set_input_bus(&mut self, bus: &[WireRef], value: u64) {
+        assert!(bus.len() <= 64, "Bus is wider than 64 bits");
+        for (i, &wire) in bus.iter().enumerate() {
+            self.set_input(wire, ((value >> i) & 1 == 1).into());
+        }
+    }
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+    pub fn iteration_count(&self) -> usize {
+        self.iteration_count
+    }
     pub fn wire(&self, wref: WireRef) -> WireState {
         self.wires[wref.0].state()
     }
+    pub fn bus(&self, bus: &[WireRef]) -> BusValue {
+        let mut result = BusValue {
+            voltages: SmallVec::with_capacity(bus.len()),
+            unstable: false,
+        };
+        for &wref in bus {
+            let state = self.wire(wref);
+            result.voltages.push(state.voltage);
+            result.unstable |= state.unstable;
+        }
+        result
+    }
+    pub fn wire_by_name(&self, path: &str) -> Option<WireRef> {
+        self.names.wire(path)
+    }
+    pub fn bus_by_name(&self, path: &str) -> Option<BusRef> {
+        self.names.bus(path)
+    }
+    pub fn component_by_name(&self, path: &str) -> Option<ComponentRef> {
+        self.names.component(path)
+    }
+    pub fn scope_of(&self, cref: ComponentRef) -> Option<&str> {
+        self.names.scope_of(cref)
+    }
+    pub fn name_of(&self, wref: WireRef) -> Option<String> {
+        self.names.name_of(wref)
+    }
+    pub fn component_name(&self, cref: ComponentRef) -> Option<&str> {
+        self.names.component_name(cref)
+    }
+    pub fn describe(&self, cref: ComponentRef) -> Option<Description> {
+        self.components[cref.0].component_impl.describe()
+    }
+    pub fn input_names(&self, cref: ComponentRef) -> Vec<String> {
+        let component = &self.components[cref.0];
+        pin_labels(&*component.component_impl, component.inputs.len(), component.outputs.len()).0
+    }
+    pub fn output_names(&self, cref: ComponentRef) -> Vec<String> {
+        let component = &self.components[cref.0];
+        pin_labels(&*component.component_impl, component.inputs.len(), component.outputs.len()).1
+    }
+}
+
+struct DebugWires<'a>(&'a Circuit);
+
+impl<'a> fmt::Debug for DebugWires<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let circuit = self.0;
+        f.debug_map().entries(circuit.wires.iter().enumerate().map(|(i, wire)| {
+            (circuit.names.wire_label(WireRef(i)), wire.state())
+        })).finish()
+    }
+}
+
+struct DebugComponents<'a>(&'a Circuit);
+
+impl<'a> fmt::Debug for DebugComponents<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let circuit = self.0;
+        f.debug_map().entries(circuit.components.iter().enumerate().map(|(i, component)| {
+            (circuit.names.component_label(ComponentRef(i)), &component.component_impl)
+        })).finish()
+    }
+}
+
+impl fmt::Debug for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Circuit")
+            .field("tick_count", &self.tick_count)
+            .field("iteration_count", &self.iteration_count)
+            .field("wires", &DebugWires(self))
+            .field("components", &DebugComponents(self))
+            .finish()
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct WireRef(usize);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WireRef(pub(crate) usize);
 
 impl WireRef {
     pub const NONE: WireRef = WireRef(NULL_INDEX);
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct ComponentRef(usize);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComponentRef(pub(crate) usize);
 
 #[derive(Default, Debug, Clone)]
-struct PreparedWire {
-    components: SmallVec<[usize; 4]>,
-    num_inputs: usize,
+pub(crate) struct PreparedWire {
+    pub(crate) components: SmallVec<[usize; 4]>,
+    pub(crate) num_inputs: usize,
+    pub(crate) external: bool,
 }
 
 impl PreparedWire {
@@ -331,9 +637,11 @@ impl PreparedWire {
             }; self.num_inputs],
             invalidation_id,
             next: NULL_INDEX,
+            watch: 0,
+            external: self.external,
         }
     }
-    fn add_input(&mut self) -> usize {
+    pub(crate) fn add_input(&mut self) -> usize {
         let result = self.num_inputs;
         self.num_inputs += 1;
         result
@@ -341,16 +649,17 @@ impl PreparedWire {
 }
 
 #[derive(Debug)]
-struct PreparedComponent {
-    inputs: SmallVec<[usize; 4]>,
-    outputs: SmallVec<[Pin; 2]>,
-    component_impl: Box<AnyComponent>,
+pub(crate) struct PreparedComponent {
+    pub(crate) inputs: SmallVec<[usize; 4]>,
+    pub(crate) outputs: SmallVec<[Pin; 2]>,
+    pub(crate) component_impl: Box<dyn AnyComponent>,
 }
 
 impl PreparedComponent {
     fn build(self) -> ComponentWrapper {
         ComponentWrapper {
             iteration: NULL_INDEX,
+            next_tick: NEVER,
             inputs: self.inputs,
             outputs: self.outputs,
             component_impl: self.component_impl,
@@ -370,8 +679,9 @@ impl Clone for PreparedComponent {
 
 #[derive(Clone, Debug, Default)]
 pub struct CircuitBuilder {
-    wires: Vec<PreparedWire>,
-    components: Vec<PreparedComponent>,
+    pub(crate) wires: Vec<PreparedWire>,
+    pub(crate) components: Vec<PreparedComponent>,
+    pub(crate) names: Names,
 }
 
 impl CircuitBuilder {
@@ -379,6 +689,7 @@ impl CircuitBuilder {
         CircuitBuilder {
             wires: Vec::new(),
             components: Vec::new(),
+            names: Names::default(),
         }
     }
     pub fn add_wire(&mut self) -> WireRef {
@@ -386,7 +697,86 @@ impl CircuitBuilder {
         self.wires.push(PreparedWire::default());
         result
     }
-    pub fn add_boxed_component(&mut self, component: Box<AnyComponent>, inputs: &[WireRef], outputs: &[WireRef]) -> ComponentRef {
+    pub fn add_bus(&mut self, width: usize) -> BusRef {
+        (0..width).map(|_| self.add_wire()).collect()
+    }
+    // Input ports are wires driven by the host via `Circuit::set_input`, and start out low
+    pub fn add_input_wire(&mut self) -> WireRef {
+        let result = self.add_wire();
+        let wire = &mut self.wires[result.0];
+        wire.external = true;
+        wire.add_input();
+        result
+    }
+    pub fn add_input_bus(&mut self, width: usize) -> BusRef {
+        (0..width).map(|_| self.add_input_wire()).collect()
+    }
+    pub fn add_named_input_wire(&mut self, name: &str) -> WireRef {
+        let result = self.add_input_wire();
+        self.name_wire(result, name);
+        result
+    }
+    pub fn add_named_input_bus(&mut self, name: &str, width: usize) -> BusRef {
+        let result = self.add_input_bus(width);
+        self.name_bus(&result, name);
+        result
+    }
+    pub fn add_named_wire(&mut self, name: &str) -> WireRef {
+        let result = self.add_wire();
+        self.name_wire(result, name);
+        result
+    }
+    pub fn add_named_bus(&mut self, name: &str, width: usize) -> BusRef {
+        let result = self.add_bus(width);
+        self.name_bus(&result, name);
+        result
+    }
+    fn check_wires(&self, component: &dyn AnyComponent, inputs: &[WireRef], outputs: &[WireRef]) -> Result<(), BuildError> {
+        for (pin, input) in inputs.iter().enumerate() {
+            if *input == WireRef::NONE {
+                return Err(BuildError::NoneInput {
+                    component: format!("{:?}", component),
+                    pin,
+                });
+            }
+            if input.0 >= self.wires.len() {
+                return Err(BuildError::UnknownWire(*input));
+            }
+        }
+        for output in outputs {
+            if *output != WireRef::NONE && output.0 >= self.wires.len() {
+                return Err(BuildError::UnknownWire(*output));
+            }
+        }
+        Ok(())
+    }
+    fn check_arity(component: &dyn AnyComponent, label: impl FnOnce() -> String, inputs: usize, outputs: usize) -> Result<(), BuildError> {
+        match component.arity() {
+            Some(expected) if !expected.matches(inputs, outputs) => Err(BuildError::PinCountMismatch {
+                component: label(),
+                expected,
+                inputs,
+                outputs,
+            }),
+            _ => Ok(()),
+        }
+    }
+    pub fn try_add_boxed_component(&mut self, component: Box<dyn AnyComponent>, inputs: &[WireRef], outputs: &[WireRef]) -> Result<ComponentRef, BuildError> {
+        self.check_wires(&*component, inputs, outputs)?;
+        Self::check_arity(&*component, || format!("{:?}", component), inputs.len(), outputs.len())?;
+        Ok(self.add_boxed_component_unchecked(component, inputs, outputs))
+    }
+    pub fn try_add_component<T: AnyComponent>(&mut self, component: T, inputs: &[WireRef], outputs: &[WireRef]) -> Result<ComponentRef, BuildError> {
+        self.try_add_boxed_component(Box::new(component), inputs, outputs)
+    }
+    // Pin counts are only checked when the circuit is built
+    pub fn add_boxed_component(&mut self, component: Box<dyn AnyComponent>, inputs: &[WireRef], outputs: &[WireRef]) -> ComponentRef {
+        if let Err(e) = self.check_wires(&*component, inputs, outputs) {
+            panic!("{}", e);
+        }
+        self.add_boxed_component_unchecked(component, inputs, outputs)
+    }
+    fn add_boxed_component_unchecked(&mut self, component: Box<dyn AnyComponent>, inputs: &[WireRef], outputs: &[WireRef]) -> ComponentRef {
         let result = ComponentRef(self.components.len());
 
         let mut comp = PreparedComponent {
@@ -417,7 +807,65 @@ impl CircuitBuilder {
     pub fn add_component<T: AnyComponent>(&mut self, component: T, inputs: &[WireRef], outputs: &[WireRef]) -> ComponentRef {
         self.add_boxed_component(Box::new(component), inputs, outputs)
     }
+    pub fn add_named_component<T: AnyComponent>(&mut self, name: &str, component: T, inputs: &[WireRef], outputs: &[WireRef]) -> ComponentRef {
+        let result = self.add_component(component, inputs, outputs);
+        self.name_component(result, name);
+        result
+    }
+    pub fn name_wire(&mut self, wire: WireRef, name: &str) -> &mut Self {
+        self.names.add_bus(name, wire.into());
+        self
+    }
+    pub fn name_bus(&mut self, bus: &[WireRef], name: &str) -> &mut Self {
+        self.names.add_bus(name, bus.into());
+        self
+    }
+    pub fn name_component(&mut self, cref: ComponentRef, name: &str) -> &mut Self {
+        self.names.add_component(name, cref);
+        self
+    }
+    pub fn wire_by_name(&self, path: &str) -> Option<WireRef> {
+        self.names.wire(path)
+    }
+    pub fn bus_by_name(&self, path: &str) -> Option<BusRef> {
+        self.names.bus(path)
+    }
+    pub fn component_by_name(&self, path: &str) -> Option<ComponentRef> {
+        self.names.component(path)
+    }
+    pub fn scope_of(&self, cref: ComponentRef) -> Option<&str> {
+        self.names.scope_of(cref)
+    }
+    pub fn name_of(&self, wref: WireRef) -> Option<String> {
+        self.names.name_of(wref)
+    }
+    pub fn component_name(&self, cref: ComponentRef) -> Option<&str> {
+        self.names.component_name(cref)
+    }
+    pub fn describe(&self, cref: ComponentRef) -> Option<Description> {
+        self.components[cref.0].component_impl.describe()
+    }
+    pub fn input_names(&self, cref: ComponentRef) -> Vec<String> {
+        let component = &self.components[cref.0];
+        pin_labels(&*component.component_impl, component.inputs.len(), component.outputs.len()).0
+    }
+    pub fn output_names(&self, cref: ComponentRef) -> Vec<String> {
+        let component = &self.components[cref.0];
+        pin_labels(&*component.component_impl, component.inputs.len(), component.outputs.len()).1
+    }
     pub fn build(self) -> Circuit {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+    pub fn try_build(self) -> Result<Circuit, BuildError> {
+        for (i, component) in self.components.iter().enumerate() {
+            Self::check_arity(
+                &*component.component_impl,
+                || self.names.component_label(ComponentRef(i)),
+                component.inputs.len(),
+                component.outputs.len(),
+            )?;
+        }
+
         let mut component_sets_map = HashMap::new();
         let wires = self.wires.into_iter().map(|w| w.build(&mut component_sets_map)).collect();
         let components = self.components.into_iter().map(PreparedComponent::build).collect();
@@ -433,14 +881,27 @@ impl CircuitBuilder {
 
         let mut circuit = Circuit {
             iteration_count: 0,
+            iteration_base: 0,
             tick_count: 0,
             first_component_set: TAIL_INDEX,
             first_wire: TAIL_INDEX,
             wires,
             component_sets,
             components,
+            names: Arc::new(self.names),
+            changes: Vec::new(),
+            observers: Observers::default(),
+            oscillation_detection: false,
+            history: None,
+            dirty_components: Vec::new(),
+            breakpoints: Breakpoints::default(),
+            components_updated: 0,
+            wires_changed: 0,
+            wake_list: WakeList::default(),
+            rescheduled: Vec::new(),
+            error_origins: None,
         };
         circuit.init();
-        circuit
+        Ok(circuit)
     }
 }
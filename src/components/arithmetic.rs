@@ -1,8 +1,9 @@
-use crate::{Voltage, Component, ComponentInterface};
+use crate::{Voltage, Component, ComponentInterface, Description, PinGroup, PinWidth};
 use crate::gates::{AndFn, XorFn, BinaryGateFn};
 
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitAdder;
 
 impl BitAdder {
@@ -31,9 +32,20 @@ impl Component for BitAdder {
             sum >>= 1;
         }
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("BitAdder", vec![
+            PinGroup::new("in", PinWidth::AtLeast(0)),
+        ], vec![
+            PinGroup::new("sum", PinWidth::AtLeast(0)),
+        ]))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HalfAdder;
 
 impl HalfAdder {
@@ -49,9 +61,22 @@ impl Component for HalfAdder {
         interface.output(0, XorFn::call(a, b));
         interface.output(1, AndFn::call(a, b));
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("HalfAdder", vec![
+            PinGroup::single("a"),
+            PinGroup::single("b"),
+        ], vec![
+            PinGroup::single("sum"),
+            PinGroup::single("carry"),
+        ]))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FullAdder;
 
 impl FullAdder {
@@ -85,9 +110,23 @@ impl Component for FullAdder {
         interface.output(0, x.into());
         interface.output(1, y.into());
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("FullAdder", vec![
+            PinGroup::single("a"),
+            PinGroup::single("b"),
+            PinGroup::single("cin"),
+        ], vec![
+            PinGroup::single("sum"),
+            PinGroup::single("cout"),
+        ]))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Adder;
 
 impl Adder {
@@ -135,10 +174,24 @@ impl Component for Adder {
         // Carry out
         interface.output(bits, carry.into());
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Adder", vec![
+            PinGroup::new("a", PinWidth::Bits(1)),
+            PinGroup::new("b", PinWidth::Bits(1)),
+            PinGroup::single("cin"),
+        ], vec![
+            PinGroup::new("sum", PinWidth::Bits(1)),
+            PinGroup::single("cout"),
+        ]))
+    }
 }
 
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Subtractor;
 
 impl Subtractor {
@@ -186,10 +239,24 @@ impl Component for Subtractor {
         // Borrow out
         interface.output(bits, borrow.into());
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Subtractor", vec![
+            PinGroup::new("a", PinWidth::Bits(1)),
+            PinGroup::new("b", PinWidth::Bits(1)),
+            PinGroup::single("bin"),
+        ], vec![
+            PinGroup::new("difference", PinWidth::Bits(1)),
+            PinGroup::single("bout"),
+        ]))
+    }
 }
 
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Multiplier;
 
 impl Multiplier {
@@ -252,10 +319,23 @@ impl Component for Multiplier {
             }
         }
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Multiplier", vec![
+            PinGroup::new("a", PinWidth::Bits(1)),
+            PinGroup::new("b", PinWidth::Bits(1)),
+            PinGroup::new("carry", PinWidth::Bits(1)),
+        ], vec![
+            PinGroup::new("product", PinWidth::Bits(2)),
+        ]))
+    }
 }
 
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Negator;
 
 impl Negator {
@@ -286,10 +366,21 @@ impl Component for Negator {
             borrow = x;
         }
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Negator", vec![
+            PinGroup::new("in", PinWidth::Bits(1)),
+        ], vec![
+            PinGroup::new("out", PinWidth::Bits(1)),
+        ]))
+    }
 }
 
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Comparator;
 
 impl Comparator {
@@ -352,9 +443,23 @@ impl Component for Comparator {
             interface.output(2, Voltage::Low.into());
         }
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Comparator", vec![
+            PinGroup::new("a", PinWidth::Bits(1)),
+            PinGroup::new("b", PinWidth::Bits(1)),
+        ], vec![
+            PinGroup::single("lt"),
+            PinGroup::single("eq"),
+            PinGroup::single("gt"),
+        ]))
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Shifter {
     select_bits: usize,
 }
@@ -363,11 +468,14 @@ impl Shifter {
     pub fn new(select_bits: usize) -> Self {
         Self { select_bits }
     }
+    pub fn select_bits(&self) -> usize {
+        self.select_bits
+    }
 }
 
 impl Component for Shifter {
     fn update(&mut self, interface: &mut ComponentInterface) {
-        let select_bits = self.select_bits as usize;
+        let select_bits = self.select_bits;
         let mut index = 0;
         for i in 0..select_bits {
             match interface.input(i) {
@@ -385,12 +493,23 @@ impl Component for Shifter {
         }
         for j in 0..(1 << select_bits) {
             if j >= index {
-                interface.output(j, interface.input(j - index).into());
+                interface.output(j, interface.input(select_bits + j - index).into());
             } else {
                 interface.output(j, Voltage::Low.into());
             }
         }
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Shifter", vec![
+            PinGroup::new("select", PinWidth::Fixed(self.select_bits)),
+            PinGroup::new("in", PinWidth::Fixed(1 << self.select_bits)),
+        ], vec![
+            PinGroup::new("out", PinWidth::Fixed(1 << self.select_bits)),
+        ]).with_param("select_bits", self.select_bits))
+    }
 }
 
 #[cfg(test)]
@@ -611,4 +730,15 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn shifter() {
+        for shift in 0..4 {
+            assert_eq!(simulate_component(
+                Shifter::new(2),
+                &[(2, shift, 0), (4, 5, 0)],
+                &[4]
+            ), [((5 << shift) & 15, 0)], "shift: {}", shift);
+        }
+    }
 }
@@ -1,11 +1,13 @@
-use crate::{Voltage, Component, ComponentInterface};
+use crate::{Voltage, Component, ComponentInterface, Description, PinGroup, StateReader, StateWriter};
 
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct Clock {
     ticks_low: u32,
     ticks_high: u32,
     tick_phase: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
     state: bool,
 }
 
@@ -28,6 +30,22 @@ impl Clock {
     pub fn state(&self) -> bool { self.state }
 }
 
+// The first tick at or after `tick` at which a clock with the given timing
+// would be in a different state to `state`
+fn next_edge(ticks_low: u32, ticks_high: u32, tick_phase: u32, state: bool, tick: u64) -> Option<u64> {
+    let period = (ticks_low + ticks_high) as u64;
+    let position = (tick + tick_phase as u64) % period;
+    if (position >= ticks_low as u64) != state {
+        Some(tick)
+    } else if ticks_low == 0 || ticks_high == 0 {
+        None
+    } else if state {
+        Some(tick + period - position)
+    } else {
+        Some(tick + ticks_low as u64 - position)
+    }
+}
+
 impl Component for Clock {
     fn tick(&mut self, tick: u64) -> bool {
         let old_state = self.state;
@@ -35,9 +53,33 @@ impl Component for Clock {
         self.state = (tick + self.tick_phase as u64) % period >= self.ticks_low as u64;
         self.state != old_state
     }
+    fn next_tick(&self, tick: u64) -> Option<u64> {
+        next_edge(self.ticks_low, self.ticks_high, self.tick_phase, self.state, tick)
+    }
     fn update(&mut self, interface: &mut ComponentInterface) {
         interface.output(0, self.state.into());
     }
+    fn is_sequential(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("Clock", vec![], vec![PinGroup::single("clk")])
+            .with_param("ticks_low", self.ticks_low)
+            .with_param("ticks_high", self.ticks_high)
+            .with_param("tick_phase", self.tick_phase))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u32(self.ticks_low);
+        state.write_u32(self.ticks_high);
+        state.write_u32(self.tick_phase);
+        state.write_bool(self.state);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.ticks_low = state.read_u32();
+        self.ticks_high = state.read_u32();
+        self.tick_phase = state.read_u32();
+        self.state = state.read_bool();
+    }
 }
 
 impl Default for Clock {
@@ -52,12 +94,20 @@ impl Default for Clock {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ControlledClock {
     ticks_low: u32,
     ticks_high: u32,
     tick_phase: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
     state: Voltage,
+    #[cfg_attr(feature = "serde", serde(skip))]
     enabled: Voltage,
+    // The last tick for which the clock was ticked, and whether it was enabled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last_tick: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last_enabled: Voltage,
 }
 
 impl ControlledClock {
@@ -85,6 +135,14 @@ impl Component for ControlledClock {
         let old_state = self.state;
         let period = self.ticks_low + self.ticks_high;
 
+        // Catch up on the ticks which were skipped whilst the clock was disabled
+        if self.last_enabled == Voltage::Low {
+            let skipped = ((tick - self.last_tick - 1) % period as u64) as u32;
+            self.tick_phase = (self.tick_phase + period - skipped) % period;
+        }
+        self.last_tick = tick;
+        self.last_enabled = self.enabled;
+
         match self.enabled {
             Voltage::Low => { self.tick_phase = (self.tick_phase + period - 1) % period; },
             Voltage::High => {},
@@ -96,10 +154,54 @@ impl Component for ControlledClock {
         }
         self.state != old_state
     }
+    fn next_tick(&self, tick: u64) -> Option<u64> {
+        if self.state == Voltage::Error {
+            None
+        } else if self.enabled != self.last_enabled {
+            Some(tick)
+        } else {
+            match self.enabled {
+                Voltage::Low => None,
+                Voltage::High => next_edge(self.ticks_low, self.ticks_high, self.tick_phase, self.state == Voltage::High, tick),
+                Voltage::Floating | Voltage::Error => Some(tick),
+            }
+        }
+    }
     fn update(&mut self, interface: &mut ComponentInterface) {
-        self.enabled = interface.input(0);
+        let enabled = interface.input(0);
+        if enabled != self.enabled {
+            self.enabled = enabled;
+            interface.reschedule();
+        }
         interface.output(0, self.state.into());
     }
+    fn is_sequential(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("ControlledClock", vec![PinGroup::single("enable")], vec![PinGroup::single("clk")])
+            .with_param("ticks_low", self.ticks_low)
+            .with_param("ticks_high", self.ticks_high)
+            .with_param("tick_phase", self.tick_phase))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u32(self.ticks_low);
+        state.write_u32(self.ticks_high);
+        state.write_u32(self.tick_phase);
+        state.write_voltage(self.state);
+        state.write_voltage(self.enabled);
+        state.write_u64(self.last_tick);
+        state.write_voltage(self.last_enabled);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.ticks_low = state.read_u32();
+        self.ticks_high = state.read_u32();
+        self.tick_phase = state.read_u32();
+        self.state = state.read_voltage();
+        self.enabled = state.read_voltage();
+        self.last_tick = state.read_u64();
+        self.last_enabled = state.read_voltage();
+    }
 }
 
 impl Default for ControlledClock {
@@ -110,6 +212,8 @@ impl Default for ControlledClock {
             tick_phase: 0,
             state: Voltage::Low,
             enabled: Voltage::High,
+            last_tick: 0,
+            last_enabled: Voltage::High,
         }
     }
 }
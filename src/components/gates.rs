@@ -1,12 +1,14 @@
 use std::marker::PhantomData;
 use std::fmt;
 
-use crate::{Voltage, VoltageInput, Component, ComponentInterface};
+use crate::{Voltage, VoltageInput, Component, ComponentInterface, Description, PinGroup, PinWidth, StateReader, StateWriter};
 
 // Constant
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Constant {
     value: VoltageInput,
+    #[cfg_attr(feature = "serde", serde(skip))]
     changed: bool,
 }
 
@@ -37,14 +39,33 @@ impl Component for Constant {
     fn tick(&mut self, _tick: u64) -> bool {
         self.changed
     }
+    fn next_tick(&self, tick: u64) -> Option<u64> {
+        if self.changed { Some(tick) } else { None }
+    }
+    fn describe(&self) -> Option<Description> {
+        let three_state = self.value.resistor || self.value.voltage == Voltage::Floating;
+        Some(Description::new("Constant", vec![], vec![PinGroup::single("out").with_three_state(three_state)])
+            .with_param("voltage", format!("{:?}", self.value.voltage))
+            .with_param("resistor", self.value.resistor))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_input(self.value);
+        state.write_bool(self.changed);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.value = state.read_input();
+        self.changed = state.read_bool();
+    }
 }
 
 // Unary gates
 pub trait UnaryGateFn: 'static + fmt::Debug {
+    const NAME: &'static str = "UnaryGate";
     fn call(a: Voltage) -> VoltageInput;
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(bound = ""))]
 pub struct UnaryGate<F: UnaryGateFn>(PhantomData<&'static F>);
 
 impl<F: UnaryGateFn> UnaryGate<F> {
@@ -58,6 +79,12 @@ impl<F: UnaryGateFn> Component for UnaryGate<F> {
         let result = F::call(interface.input(0));
         interface.output(0, result);
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new(F::NAME, vec![PinGroup::single("in")], vec![PinGroup::single("out")]))
+    }
 }
 
 impl<F: UnaryGateFn> Default for UnaryGate<F> {
@@ -76,6 +103,7 @@ impl<F: UnaryGateFn> Clone for UnaryGate<F> {
 #[derive(Debug)]
 pub struct IdentityFn;
 impl UnaryGateFn for IdentityFn {
+    const NAME: &'static str = "Buffer";
     fn call(a: Voltage) -> VoltageInput {
         a.into()
     }
@@ -86,6 +114,7 @@ pub type Buffer = UnaryGate<IdentityFn>;
 #[derive(Debug)]
 pub struct NotFn;
 impl UnaryGateFn for NotFn {
+    const NAME: &'static str = "NotGate";
     fn call(a: Voltage) -> VoltageInput {
         match a {
             Voltage::Low => Voltage::High,
@@ -98,10 +127,16 @@ pub type NotGate = UnaryGate<NotFn>;
 
 // Binary gates
 pub trait BinaryGateFn: 'static + fmt::Debug {
+    const NAME: &'static str = "BinaryGate";
+    const NARY_NAME: &'static str = "NaryGate";
+    const INPUT_NAMES: [&'static str; 2] = ["a", "b"];
+    // Whether the output can float
+    const THREE_STATE: bool = false;
     fn call(a: Voltage, b: Voltage) -> VoltageInput;
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(bound = ""))]
 pub struct BinaryGate<F: BinaryGateFn>(PhantomData<&'static F>);
 
 impl<F: BinaryGateFn> BinaryGate<F> {
@@ -115,6 +150,15 @@ impl<F: BinaryGateFn> Component for BinaryGate<F> {
         let result = F::call(interface.input(0), interface.input(1));
         interface.output(0, result);
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new(F::NAME, vec![
+            PinGroup::single(F::INPUT_NAMES[0]),
+            PinGroup::single(F::INPUT_NAMES[1]),
+        ], vec![PinGroup::single("out").with_three_state(F::THREE_STATE)]))
+    }
 }
 
 impl<F: BinaryGateFn> Default for BinaryGate<F> {
@@ -133,6 +177,8 @@ impl<F: BinaryGateFn> Clone for BinaryGate<F> {
 #[derive(Debug)]
 pub struct AndFn;
 impl BinaryGateFn for AndFn {
+    const NAME: &'static str = "AndGate";
+    const NARY_NAME: &'static str = "NaryAndGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         if a == Voltage::Low || b == Voltage::Low {
             Voltage::Low
@@ -149,6 +195,8 @@ pub type AndGate = BinaryGate<AndFn>;
 #[derive(Debug)]
 pub struct OrFn;
 impl BinaryGateFn for OrFn {
+    const NAME: &'static str = "OrGate";
+    const NARY_NAME: &'static str = "NaryOrGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         if a == Voltage::High || b == Voltage::High {
             Voltage::High
@@ -165,6 +213,8 @@ pub type OrGate = BinaryGate<OrFn>;
 #[derive(Debug)]
 pub struct XorFn;
 impl BinaryGateFn for XorFn {
+    const NAME: &'static str = "XorGate";
+    const NARY_NAME: &'static str = "ParityGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         match (a, b) {
             (Voltage::Low, Voltage::High) | (Voltage::High, Voltage::Low) => Voltage::High,
@@ -179,6 +229,7 @@ pub type XorGate = BinaryGate<XorFn>;
 #[derive(Debug)]
 pub struct NandFn;
 impl BinaryGateFn for NandFn {
+    const NAME: &'static str = "NandGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         if a == Voltage::Low || b == Voltage::Low {
             Voltage::High
@@ -195,6 +246,7 @@ pub type NandGate = BinaryGate<NandFn>;
 #[derive(Debug)]
 pub struct NorFn;
 impl BinaryGateFn for NorFn {
+    const NAME: &'static str = "NorGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         if a == Voltage::High || b == Voltage::High {
             Voltage::Low
@@ -211,6 +263,7 @@ pub type NorGate = BinaryGate<NorFn>;
 #[derive(Debug)]
 pub struct XnorFn;
 impl BinaryGateFn for XnorFn {
+    const NAME: &'static str = "XnorGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         match (a, b) {
             (Voltage::Low, Voltage::High) | (Voltage::High, Voltage::Low) => Voltage::Low,
@@ -225,6 +278,7 @@ pub type XnorGate = BinaryGate<XnorFn>;
 #[derive(Debug)]
 pub struct ImplyFn;
 impl BinaryGateFn for ImplyFn {
+    const NAME: &'static str = "ImplyGate";
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         if a == Voltage::Low || b == Voltage::High {
             Voltage::High
@@ -241,6 +295,9 @@ pub type ImplyGate = BinaryGate<ImplyFn>;
 #[derive(Debug)]
 pub struct ControlFn;
 impl BinaryGateFn for ControlFn {
+    const NAME: &'static str = "ControlledBuffer";
+    const INPUT_NAMES: [&'static str; 2] = ["in", "enable"];
+    const THREE_STATE: bool = true;
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         match b {
             Voltage::High => a,
@@ -255,6 +312,9 @@ pub type ControlledBuffer = BinaryGate<ControlFn>;
 #[derive(Debug)]
 pub struct InvertedControlFn;
 impl BinaryGateFn for InvertedControlFn {
+    const NAME: &'static str = "ControlledInverter";
+    const INPUT_NAMES: [&'static str; 2] = ["in", "enable"];
+    const THREE_STATE: bool = true;
     fn call(a: Voltage, b: Voltage) -> VoltageInput {
         match (a, b) {
             (Voltage::Low, Voltage::High) => Voltage::High,
@@ -269,6 +329,7 @@ pub type ControlledInverter = BinaryGate<InvertedControlFn>;
 
 // N-ary gates
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(bound = ""))]
 pub struct NaryGate<F: BinaryGateFn>(PhantomData<&'static F>);
 
 impl<F: BinaryGateFn> NaryGate<F> {
@@ -285,6 +346,12 @@ impl<F: BinaryGateFn> Component for NaryGate<F> {
         }
         interface.output(0, result);
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new(F::NARY_NAME, vec![PinGroup::new("in", PinWidth::AtLeast(2))], vec![PinGroup::single("out")]))
+    }
 }
 
 impl<F: BinaryGateFn> Default for NaryGate<F> {
@@ -302,3 +369,73 @@ impl<F: BinaryGateFn> Clone for NaryGate<F> {
 pub type NaryAndGate = NaryGate<AndFn>;
 pub type NaryOrGate = NaryGate<OrFn>;
 pub type ParityGate = NaryGate<XorFn>;
+
+
+// Lookup table, described by a list of rows with one entry per input, where `None`
+// matches either voltage. The output is `value` when the inputs match any of the
+// rows, and the opposite otherwise.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LookupTable {
+    inputs: usize,
+    rows: Vec<Vec<Option<bool>>>,
+    value: bool,
+}
+
+impl LookupTable {
+    pub fn new(inputs: usize, rows: Vec<Vec<Option<bool>>>, value: bool) -> Self {
+        assert!(rows.iter().all(|row| row.len() == inputs), "Lookup table rows must have one entry per input");
+        LookupTable { inputs, rows, value }
+    }
+    pub fn inputs(&self) -> usize { self.inputs }
+    pub fn rows(&self) -> &[Vec<Option<bool>>] { &self.rows }
+    pub fn value(&self) -> bool { self.value }
+}
+
+impl Component for LookupTable {
+    fn update(&mut self, interface: &mut ComponentInterface) {
+        // `None` if the result depends on an input which is not high or low
+        let mut matched = Some(false);
+        for row in &self.rows {
+            let mut row_matched = Some(true);
+            for (i, entry) in row.iter().enumerate() {
+                if let Some(expected) = *entry {
+                    let voltage = interface.input(i);
+                    match voltage {
+                        Voltage::Low | Voltage::High if (voltage == Voltage::High) == expected => {},
+                        Voltage::Low | Voltage::High => {
+                            row_matched = Some(false);
+                            break;
+                        },
+                        _ => row_matched = None,
+                    }
+                }
+            }
+            match row_matched {
+                Some(true) => {
+                    matched = Some(true);
+                    break;
+                },
+                Some(false) => {},
+                None => matched = None,
+            }
+        }
+        interface.output(0, match matched {
+            Some(matched) => (matched == self.value).into(),
+            None => Voltage::Error.into(),
+        });
+    }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        let rows: Vec<String> = self.rows.iter().map(|row| row.iter().map(|entry| match entry {
+            Some(true) => '1',
+            Some(false) => '0',
+            None => '-',
+        }).collect()).collect();
+        Some(Description::new("LookupTable", vec![PinGroup::new("in", PinWidth::Fixed(self.inputs))], vec![PinGroup::single("out")])
+            .with_param("rows", rows.join(" "))
+            .with_param("value", self.value))
+    }
+}
@@ -1,10 +1,14 @@
-use crate::{Voltage, Component, ComponentInterface};
+use crate::{Voltage, Component, ComponentInterface, Description, PinGroup, StateReader, StateWriter};
+use crate::components::sample;
 
 
 // SR-NOR latch
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct SrNorLatch {
+    #[cfg_attr(feature = "serde", serde(skip))]
     s: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     r: bool,
 }
 
@@ -34,6 +38,29 @@ impl Component for SrNorLatch {
             interface.output(1, Voltage::Error.into());
         }
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn is_sequential(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("SrNorLatch", vec![
+            PinGroup::single("s"),
+            PinGroup::single("r"),
+        ], vec![
+            PinGroup::single("q"),
+            PinGroup::single("nq"),
+        ]))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.s);
+        state.write_bool(self.r);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.s = state.read_bool();
+        self.r = state.read_bool();
+    }
 }
 
 impl Default for SrNorLatch {
@@ -44,3 +71,67 @@ impl Default for SrNorLatch {
         }
     }
 }
+
+// D latch, which passes `d` through whilst `enable` is active and holds it otherwise
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct DLatch {
+    active_low: bool,
+    initial: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    state: Option<Voltage>,
+}
+
+impl DLatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn set_active_low(&mut self, active_low: bool) -> &mut Self {
+        self.active_low = active_low;
+        self
+    }
+    pub fn set_initial(&mut self, initial: bool) -> &mut Self {
+        self.initial = initial;
+        self
+    }
+    pub fn active_low(&self) -> bool { self.active_low }
+    pub fn initial(&self) -> bool { self.initial }
+    pub fn state(&self) -> Voltage { self.state.unwrap_or_else(|| self.initial.into()) }
+}
+
+impl Component for DLatch {
+    fn update(&mut self, interface: &mut ComponentInterface) {
+        match (interface.input(1), self.active_low) {
+            (Voltage::High, false) | (Voltage::Low, true) => {
+                self.state = Some(sample(interface.input(0)));
+            },
+            (Voltage::Low, false) | (Voltage::High, true) => {},
+            _ => { self.state = Some(Voltage::Error); },
+        }
+        interface.output(0, self.state().into());
+    }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn is_sequential(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("DLatch", vec![
+            PinGroup::single("d"),
+            PinGroup::single("enable"),
+        ], vec![PinGroup::single("q")])
+            .with_param("active_low", self.active_low)
+            .with_param("initial", self.initial))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.active_low);
+        state.write_bool(self.initial);
+        state.write_voltage(self.state());
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.active_low = state.read_bool();
+        self.initial = state.read_bool();
+        self.state = Some(state.read_voltage());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{Voltage, Component, ComponentInterface, Description, PinGroup, StateReader, StateWriter};
+use crate::components::sample;
+
+
+// D flip-flop, which stores `d` on each rising (or falling) edge of `clk`
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct DFlipFlop {
+    falling_edge: bool,
+    initial: bool,
+    // `None` until the first edge, when the initial value is output
+    #[cfg_attr(feature = "serde", serde(skip))]
+    state: Option<Voltage>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock: Voltage,
+}
+
+impl DFlipFlop {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn set_falling_edge(&mut self, falling_edge: bool) -> &mut Self {
+        self.falling_edge = falling_edge;
+        self
+    }
+    pub fn set_initial(&mut self, initial: bool) -> &mut Self {
+        self.initial = initial;
+        self
+    }
+    pub fn falling_edge(&self) -> bool { self.falling_edge }
+    pub fn initial(&self) -> bool { self.initial }
+    pub fn state(&self) -> Voltage { self.state.unwrap_or_else(|| self.initial.into()) }
+}
+
+impl Component for DFlipFlop {
+    fn update(&mut self, interface: &mut ComponentInterface) {
+        let clock = interface.input(1);
+        let (from, to) = if self.falling_edge {
+            (Voltage::High, Voltage::Low)
+        } else {
+            (Voltage::Low, Voltage::High)
+        };
+        // The clock starts out floating, so the first clock level seen is not an edge
+        if self.clock == from && clock == to {
+            self.state = Some(sample(interface.input(0)));
+        }
+        self.clock = clock;
+        interface.output(0, self.state().into());
+    }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn is_sequential(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> Option<Description> {
+        Some(Description::new("DFlipFlop", vec![
+            PinGroup::single("d"),
+            PinGroup::single("clk"),
+        ], vec![PinGroup::single("q")])
+            .with_param("falling_edge", self.falling_edge)
+            .with_param("initial", self.initial))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.falling_edge);
+        state.write_bool(self.initial);
+        state.write_voltage(self.state());
+        state.write_voltage(self.clock);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.falling_edge = state.read_bool();
+        self.initial = state.read_bool();
+        self.state = Some(state.read_voltage());
+        self.clock = state.read_voltage();
+    }
+}
+
+impl Default for DFlipFlop {
+    fn default() -> Self {
+        Self {
+            falling_edge: false,
+            initial: false,
+            state: None,
+            clock: Voltage::Floating,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CircuitBuilder;
+    use crate::latches::DLatch;
+
+    #[test]
+    fn shift_register() {
+        let mut builder = CircuitBuilder::new();
+        let d = builder.add_input_wire();
+        let clk = builder.add_input_wire();
+        let q = builder.add_bus(2);
+        let latched = builder.add_wire();
+        let first = builder.add_component(*DFlipFlop::new().set_initial(true), &[d, clk], &[q[0]]);
+        builder.add_component(DFlipFlop::new(), &[q[0], clk], &[q[1]]);
+        let latch = builder.add_component(*DLatch::new().set_active_low(true), &[d, clk], &[latched]);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus(&q).to_u64(), Some(1));
+        assert_eq!(circuit.wire(latched).voltage, Voltage::Low);
+
+        // Both flip-flops sample their inputs from before the edge
+        for &(input, expected) in &[(false, 2), (true, 1), (true, 3)] {
+            circuit.set_input(d, input.into());
+            circuit.set_input(clk, Voltage::High.into());
+            assert!(circuit.propagate(10).is_stable());
+            assert_eq!(circuit.bus(&q).to_u64(), Some(expected));
+            circuit.set_input(clk, Voltage::Low.into());
+            assert!(circuit.propagate(10).is_stable());
+        }
+
+        // The latch holds its value on the rising edge, whilst the flip-flops sample theirs
+        assert_eq!(circuit.wire(latched).voltage, Voltage::High);
+        circuit.set_input(clk, Voltage::High.into());
+        circuit.set_input(d, Voltage::Low.into());
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(latched).voltage, Voltage::High);
+        assert_eq!(circuit.bus(&q).to_u64(), Some(2));
+
+        // Snapshots include the configuration
+        let snapshot = circuit.snapshot();
+        circuit.modify_component(first, |flip_flop: &mut DFlipFlop| { flip_flop.set_falling_edge(true); });
+        circuit.modify_component(latch, |latch: &mut DLatch| { latch.set_active_low(false); });
+        circuit.restore(&snapshot);
+        assert!(!circuit.component_ref::<DFlipFlop>(first).falling_edge());
+        assert!(circuit.component_ref::<DLatch>(latch).active_low());
+    }
+}
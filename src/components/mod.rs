@@ -3,3 +3,14 @@ pub mod latches;
 pub mod clocks;
 pub mod plexers;
 pub mod arithmetic;
+pub mod memory;
+
+use crate::Voltage;
+
+// Sampled data inputs which are not high or low are stored as an error
+pub(crate) fn sample(voltage: Voltage) -> Voltage {
+    match voltage {
+        Voltage::Low | Voltage::High => voltage,
+        _ => Voltage::Error,
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{Voltage, Component, ComponentInterface};
+use crate::{Voltage, Component, ComponentInterface, Description, PinGroup, PinWidth, StateReader, StateWriter};
 
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Multiplexer {
     select_bits: u32,
 }
@@ -33,12 +34,24 @@ impl Component for Multiplexer {
         }
         interface.output(0, interface.input(select_bits + index).into());
     }
+    fn next_tick(&self, _tick: u64) -> Option<u64> {
+        None
+    }
+    fn describe(&self) -> Option<Description> {
+        let select_bits = self.select_bits as usize;
+        Some(Description::new("Multiplexer", vec![
+            PinGroup::new("select", PinWidth::Fixed(select_bits)),
+            PinGroup::new("in", PinWidth::Fixed(1 << select_bits)),
+        ], vec![PinGroup::single("out")]).with_param("select_bits", select_bits))
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Demultiplexer {
     select_bits: u32,
     three_state: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     changed: bool,
 }
 
@@ -82,23 +95,45 @@ impl Component for Demultiplexer {
         }
         for j in 0..(1 << select_bits) {
             if j == index {
-                interface.output(index, interface.input(select_bits).into());
+                interface.output(j, interface.input(select_bits).into());
             } else if self.three_state {
-                interface.output(index, Voltage::Floating.into());
+                interface.output(j, Voltage::Floating.into());
             } else {
-                interface.output(index, Voltage::Low.into());
+                interface.output(j, Voltage::Low.into());
             }
         }
     }
     fn tick(&mut self, _tick: u64) -> bool {
         self.changed
     }
+    fn next_tick(&self, tick: u64) -> Option<u64> {
+        if self.changed { Some(tick) } else { None }
+    }
+    fn describe(&self) -> Option<Description> {
+        let select_bits = self.select_bits as usize;
+        Some(Description::new("Demultiplexer", vec![
+            PinGroup::new("select", PinWidth::Fixed(select_bits)),
+            PinGroup::single("in"),
+        ], vec![PinGroup::new("out", PinWidth::Fixed(1 << select_bits)).with_three_state(self.three_state)])
+            .with_param("select_bits", select_bits)
+            .with_param("three_state", self.three_state))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.three_state);
+        state.write_bool(self.changed);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.three_state = state.read_bool();
+        self.changed = state.read_bool();
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PriorityEncoder {
     select_bits: u32,
     inverted: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     changed: bool,
 }
 
@@ -164,4 +199,52 @@ impl Component for PriorityEncoder {
     fn tick(&mut self, _tick: u64) -> bool {
         self.changed
     }
+    fn next_tick(&self, tick: u64) -> Option<u64> {
+        if self.changed { Some(tick) } else { None }
+    }
+    fn describe(&self) -> Option<Description> {
+        let select_bits = self.select_bits as usize;
+        Some(Description::new("PriorityEncoder", vec![
+            PinGroup::new("in", PinWidth::Fixed(1 << select_bits)),
+        ], vec![
+            PinGroup::single("valid"),
+            PinGroup::new("index", PinWidth::Fixed(select_bits)).with_three_state(true),
+        ]).with_param("select_bits", select_bits).with_param("inverted", self.inverted))
+    }
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bool(self.inverted);
+        state.write_bool(self.changed);
+    }
+    fn load_state(&mut self, state: &mut StateReader) {
+        self.inverted = state.read_bool();
+        self.changed = state.read_bool();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn multiplexer() {
+        for select in 0..4 {
+            assert_eq!(simulate_component(
+                Multiplexer::new(2),
+                &[(2, select, 0), (4, 0b0110, 0)],
+                &[1]
+            ), [((0b0110 >> select) & 1, 0)]);
+        }
+    }
+
+    #[test]
+    fn demultiplexer() {
+        for select in 0..4 {
+            assert_eq!(simulate_component(
+                Demultiplexer::new(2),
+                &[(2, select, 0), (1, 1, 0)],
+                &[4]
+            ), [(1 << select, 0)]);
+        }
+    }
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use smallvec::SmallVec;
+
+use crate::{BreakpointHit, Circuit, ComponentRef, Voltage, VoltageInput, WireRef};
+use crate::circuit::{NULL_INDEX, TAIL_INDEX};
+
+// A feedback loop: `components[i]` drives `wires[i]`, which feeds `components[i+1]`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cycle {
+    pub wires: Vec<WireRef>,
+    pub components: Vec<ComponentRef>,
+}
+
+// A repeating pattern of wire changes found by oscillation detection
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Oscillation {
+    // Number of iterations before the queued wires return to the same state
+    pub period: usize,
+    pub wires: Vec<WireRef>,
+    pub components: Vec<ComponentRef>,
+}
+
+// The queued wires after each iteration, mapped to the iteration count at which they were seen
+type QueueState = Vec<(usize, Voltage, SmallVec<[VoltageInput; 2]>)>;
+pub(crate) type QueueStates = HashMap<QueueState, usize>;
+
+#[must_use]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PropagateResult {
+    pub iterations: usize,
+    // The remaining fields are only populated if the circuit did not stabilize
+    pub unstable_wires: Vec<WireRef>,
+    pub last_updated: Vec<ComponentRef>,
+    pub cycle: Option<Cycle>,
+    // Only populated when oscillation detection is enabled
+    pub oscillation: Option<Oscillation>,
+    // Set if a breakpoint fired, in which case propagation stopped at the end of that iteration
+    pub breakpoint: Option<BreakpointHit>,
+}
+
+impl PropagateResult {
+    pub(crate) fn stable(iterations: usize) -> Self {
+        PropagateResult {
+            iterations,
+            ..Default::default()
+        }
+    }
+    pub fn is_stable(&self) -> bool {
+        self.unstable_wires.is_empty()
+    }
+}
+
+impl Circuit {
+    pub(crate) fn pending_wire_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut wire_id = self.first_wire;
+        std::iter::from_fn(move || {
+            if wire_id == TAIL_INDEX {
+                None
+            } else {
+                let result = wire_id;
+                wire_id = self.wires[wire_id].next;
+                Some(result)
+            }
+        })
+    }
+    // Wires whose inputs have changed, and which will be updated by the next iteration
+    pub fn pending_wires(&self) -> impl Iterator<Item = WireRef> + '_ {
+        self.pending_wire_ids().map(WireRef)
+    }
+    // Components which will be updated before the next iteration, either because they
+    // were modified via `modify_component`, or because one of their inputs changed.
+    pub fn pending_components(&self) -> impl Iterator<Item = ComponentRef> + '_ {
+        let mut component_set = self.first_component_set;
+        let mut components = self.dirty_components.clone();
+        while component_set != TAIL_INDEX {
+            components.extend_from_slice(&self.component_sets[component_set].components);
+            component_set = self.component_sets[component_set].next;
+        }
+        components.sort_unstable();
+        components.dedup();
+        components.into_iter().map(ComponentRef)
+    }
+    // Whether the component has been updated since the given iteration of the current tick
+    fn updated_since(&self, component_id: usize, iteration: usize) -> bool {
+        let stamp = self.components[component_id].iteration;
+        stamp != NULL_INDEX && stamp >= self.iteration_base + iteration
+    }
+    // Components which were updated during the most recent iteration
+    fn last_updated(&self) -> Vec<ComponentRef> {
+        if self.iteration_count == 0 {
+            return Vec::new();
+        }
+        (0..self.components.len())
+            .filter(|&i| self.updated_since(i, self.iteration_count - 1))
+            .map(ComponentRef).collect()
+    }
+    // Result of a call to `propagate` which stopped before the circuit was stable
+    pub(crate) fn pending_result(&mut self, iterations: usize) -> PropagateResult {
+        PropagateResult {
+            iterations,
+            unstable_wires: self.pending_wire_ids().map(WireRef).collect(),
+            last_updated: self.last_updated(),
+            breakpoint: self.breakpoints.hit.take(),
+            ..Default::default()
+        }
+    }
+    pub(crate) fn unstable_result(&self, first_iteration: usize) -> PropagateResult {
+        let iterations = self.iteration_count() - first_iteration;
+        let unstable_wires = self.pending_wire_ids().map(WireRef).collect();
+        let last_updated = self.last_updated();
+
+        // Look for loops amongst the components which were active in the second half of the run
+        let threshold = first_iteration + iterations/2;
+        let cycle = self.find_cycle(|component_id| self.updated_since(component_id, threshold));
+
+        PropagateResult {
+            iterations,
+            unstable_wires,
+            last_updated,
+            cycle,
+            oscillation: None,
+            breakpoint: None,
+        }
+    }
+    // When enabled, `propagate` stops as soon as the queued wires repeat a previous
+    // state, and reports the oscillation in its result. This has a cost on every
+    // iteration, so is disabled by default.
+    pub fn detect_oscillation(&mut self, enabled: bool) -> &mut Self {
+        self.oscillation_detection = enabled;
+        self
+    }
+    pub(crate) fn check_oscillation(&self, seen: &mut QueueStates) -> Option<Oscillation> {
+        let mut state: QueueState = self.pending_wire_ids().map(|wire_id| {
+            let wire = &self.wires[wire_id];
+            (wire_id, wire.voltage, wire.inputs.clone())
+        }).collect();
+        state.sort_by_key(|entry| entry.0);
+
+        let previous = *seen.entry(state).or_insert(self.iteration_count());
+        if previous == self.iteration_count() {
+            return None;
+        }
+
+        // Every component in the loop was updated at least once since the state was last seen
+        let active = |component_id: usize| self.updated_since(component_id, previous);
+        let (wires, components) = match self.find_cycle(active) {
+            Some(cycle) => (cycle.wires, cycle.components),
+            None => (
+                self.pending_wire_ids().map(WireRef).collect(),
+                (0..self.components.len()).filter(|&i| active(i)).map(ComponentRef).collect(),
+            ),
+        };
+        Some(Oscillation {
+            period: self.iteration_count() - previous,
+            wires,
+            components,
+        })
+    }
+    // Finds a loop of components via the wires connecting them, only considering
+    // components for which `include` returns true.
+    pub(crate) fn find_cycle<F: Fn(usize) -> bool>(&self, include: F) -> Option<Cycle> {
+        const VISITING: u8 = 1;
+        const DONE: u8 = 2;
+
+        let mut state: HashMap<usize, u8> = HashMap::new();
+        for start in (0..self.components.len()).filter(|&i| include(i)) {
+            if state.contains_key(&start) {
+                continue;
+            }
+            // Stack of (component, wire leading to it, output pin index, reader index)
+            let mut stack = vec![(start, NULL_INDEX, 0, 0)];
+            state.insert(start, VISITING);
+            while let Some(&mut (component_id, _, ref mut pin, ref mut reader)) = stack.last_mut() {
+                let outputs = &self.components[component_id].outputs;
+                let mut next = None;
+                while *pin < outputs.len() {
+                    let wire_id = outputs[*pin].wire_id;
+                    let readers: &[usize] = match self.wires.get(wire_id) {
+                        Some(wire) if wire.invalidation_id != NULL_INDEX => {
+                            &self.component_sets[wire.invalidation_id].components
+                        },
+                        _ => &[],
+                    };
+                    if *reader < readers.len() {
+                        let candidate = readers[*reader];
+                        *reader += 1;
+                        if include(candidate) {
+                            next = Some((candidate, wire_id));
+                            break;
+                        }
+                    } else {
+                        *pin += 1;
+                        *reader = 0;
+                    }
+                }
+                match next {
+                    Some((candidate, wire_id)) => match state.get(&candidate) {
+                        None => {
+                            state.insert(candidate, VISITING);
+                            stack.push((candidate, wire_id, 0, 0));
+                        },
+                        Some(&VISITING) => {
+                            // Found a loop, unwind the stack to recover it
+                            let position = stack.iter().position(|entry| entry.0 == candidate).unwrap();
+                            let mut cycle = Cycle::default();
+                            for (i, entry) in stack[position..].iter().enumerate() {
+                                cycle.components.push(ComponentRef(entry.0));
+                                cycle.wires.push(match stack.get(position + i + 1) {
+                                    Some(next_entry) => WireRef(next_entry.1),
+                                    None => WireRef(wire_id),
+                                });
+                            }
+                            return Some(cycle);
+                        },
+                        Some(_) => {},
+                    },
+                    None => {
+                        state.insert(component_id, DONE);
+                        stack.pop();
+                    },
+                }
+            }
+        }
+        None
+    }
+}
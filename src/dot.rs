@@ -0,0 +1,236 @@
+// Graphviz DOT output, for visualising the structure of a circuit
+use std::io::{self, Write};
+
+use crate::{AnyComponent, Circuit, CircuitBuilder, ComponentRef, Voltage, WireRef};
+use crate::circuit::{NULL_INDEX, Pin};
+use crate::names::Names;
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// The same colours Logisim uses for wires
+fn voltage_color(voltage: Voltage) -> &'static str {
+    match voltage {
+        Voltage::Low => "darkgreen",
+        Voltage::High => "green",
+        Voltage::Floating => "blue",
+        Voltage::Error => "red",
+    }
+}
+
+struct Graph<'a> {
+    names: &'a Names,
+    components: Vec<(&'a dyn AnyComponent, &'a [usize], &'a [Pin])>,
+    external: Vec<bool>,
+    voltages: Option<Vec<Voltage>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DotExporter {
+    clusters: bool,
+    voltages: bool,
+}
+
+impl DotExporter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    // When set, the components of each subcircuit instance are grouped into a cluster
+    pub fn set_clusters(&mut self, clusters: bool) -> &mut Self {
+        self.clusters = clusters;
+        self
+    }
+    // When set, wires are coloured by their current voltage. This only applies to
+    // a `Circuit`, as a `CircuitBuilder` has not been simulated yet.
+    pub fn set_voltages(&mut self, voltages: bool) -> &mut Self {
+        self.voltages = voltages;
+        self
+    }
+    pub fn write_builder<W: Write>(&self, builder: &CircuitBuilder, out: W) -> io::Result<()> {
+        self.write(&Graph {
+            names: &builder.names,
+            components: builder.components.iter()
+                .map(|component| (&*component.component_impl, &component.inputs[..], &component.outputs[..]))
+                .collect(),
+            external: builder.wires.iter().map(|wire| wire.external).collect(),
+            voltages: None,
+        }, out)
+    }
+    pub fn write_circuit<W: Write>(&self, circuit: &Circuit, out: W) -> io::Result<()> {
+        self.write(&Graph {
+            names: &circuit.names,
+            components: circuit.components.iter()
+                .map(|component| (&*component.component_impl, &component.inputs[..], &component.outputs[..]))
+                .collect(),
+            external: circuit.wires.iter().map(|wire| wire.external).collect(),
+            voltages: if self.voltages {
+                Some(circuit.wires.iter().map(|wire| wire.voltage).collect())
+            } else {
+                None
+            },
+        }, out)
+    }
+    fn write_component<W: Write>(&self, graph: &Graph, out: &mut W, component_id: usize, indent: &str) -> io::Result<()> {
+        let component = graph.components[component_id].0;
+        let mut label = match component.describe() {
+            Some(description) => description.label(),
+            None => format!("{:?}", component),
+        };
+        if let Some(name) = graph.names.component_name(ComponentRef(component_id)) {
+            label = format!("{}\n{}", name, label);
+        }
+        writeln!(out, "{}c{} [label=\"{}\"];", indent, component_id, escape(&label))
+    }
+    // Writes the components in the given scope, and any nested subcircuit instances as clusters
+    fn write_scope<W: Write>(&self, graph: &Graph, out: &mut W, scope: Option<&str>, scopes: &[Option<&str>], depth: usize) -> io::Result<()> {
+        let indent = "    ".repeat(depth);
+        for (i, _) in scopes.iter().enumerate().filter(|(_, s)| **s == scope) {
+            self.write_component(graph, out, i, &indent)?;
+        }
+        let paths: Vec<&str> = graph.names.instance_paths().collect();
+        let parent_of = |path: &str| paths.iter().cloned()
+            .filter(|parent| path.len() > parent.len() && path.starts_with(parent) && path[parent.len()..].starts_with('.'))
+            .max_by_key(|parent| parent.len());
+        for (i, &path) in paths.iter().enumerate() {
+            if parent_of(path) == scope {
+                writeln!(out, "{}subgraph cluster_{} {{", indent, i)?;
+                writeln!(out, "{}    label=\"{}\";", indent, escape(path))?;
+                self.write_scope(graph, out, Some(path), scopes, depth + 1)?;
+                writeln!(out, "{}}}", indent)?;
+            }
+        }
+        Ok(())
+    }
+    fn write<W: Write>(&self, graph: &Graph, mut out: W) -> io::Result<()> {
+        writeln!(out, "digraph circuit {{")?;
+        writeln!(out, "    rankdir=LR;")?;
+        writeln!(out, "    node [shape=box];")?;
+
+        if self.clusters {
+            let scopes: Vec<Option<&str>> = (0..graph.components.len())
+                .map(|i| graph.names.scope_of(ComponentRef(i)))
+                .collect();
+            self.write_scope(graph, &mut out, None, &scopes, 1)?;
+        } else {
+            for i in 0..graph.components.len() {
+                self.write_component(graph, &mut out, i, "    ")?;
+            }
+        }
+
+        let mut drivers = vec![Vec::new(); graph.external.len()];
+        let mut readers = vec![Vec::new(); graph.external.len()];
+        for (i, &(_, inputs, outputs)) in graph.components.iter().enumerate() {
+            for pin in outputs {
+                if pin.wire_id != NULL_INDEX && !drivers[pin.wire_id].contains(&i) {
+                    drivers[pin.wire_id].push(i);
+                }
+            }
+            for &wire_id in inputs {
+                if !readers[wire_id].contains(&i) {
+                    readers[wire_id].push(i);
+                }
+            }
+        }
+
+        // Wires which are not connected to a component at one end get a node of their own
+        for wire_id in 0..graph.external.len() {
+            let name = graph.names.name_of(WireRef(wire_id));
+            let (wire_drivers, wire_readers) = (&drivers[wire_id], &readers[wire_id]);
+            let port = graph.external[wire_id] || wire_drivers.is_empty() || wire_readers.is_empty();
+            if wire_drivers.is_empty() && wire_readers.is_empty() && !graph.external[wire_id] {
+                continue;
+            }
+            if port {
+                let label = name.clone().unwrap_or_else(|| format!("w{}", wire_id));
+                writeln!(out, "    w{} [shape=plaintext, label=\"{}\"];", wire_id, escape(&label))?;
+            }
+
+            let mut sources: Vec<String> = wire_drivers.iter().map(|i| format!("c{}", i)).collect();
+            let mut sinks: Vec<String> = wire_readers.iter().map(|i| format!("c{}", i)).collect();
+            if graph.external[wire_id] || sources.is_empty() {
+                sources.push(format!("w{}", wire_id));
+            } else if sinks.is_empty() {
+                sinks.push(format!("w{}", wire_id));
+            }
+
+            let mut attributes = Vec::new();
+            if let Some(name) = &name {
+                attributes.push(format!("label=\"{}\"", escape(name)));
+            }
+            if let Some(voltages) = &graph.voltages {
+                attributes.push(format!("color={}", voltage_color(voltages[wire_id])));
+            }
+            let attributes = if attributes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attributes.join(", "))
+            };
+            for source in &sources {
+                for sink in &sinks {
+                    writeln!(out, "    {} -> {}{};", source, sink, attributes)?;
+                }
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubcircuitDef;
+    use crate::gates::*;
+    use crate::plexers::*;
+
+    #[test]
+    fn export() {
+        let mut inverter = SubcircuitDef::new();
+        let a = inverter.add_input("a", 1);
+        let out = inverter.add_output("out", 1);
+        inverter.add_component(NotGate::new(), &a, &out);
+
+        let mut builder = CircuitBuilder::new();
+        let select = builder.add_named_input_wire("select");
+        let data = builder.add_named_bus("data", 2);
+        let out = builder.add_named_wire("out");
+        let inverted = builder.add_wire();
+        builder.add_component(Constant::new(Voltage::High.into()), &[], &[data[0]]);
+        builder.add_subcircuit("inv", &inverter, &[&[data[0]]], &[&[data[1]]]);
+        builder.add_named_component("mux", Multiplexer::new(1), &[select, data[0], data[1]], &[out]);
+        builder.add_component(NotGate::new(), &[out], &[inverted]);
+
+        let mut dot = Vec::new();
+        DotExporter::new().set_clusters(true).write_builder(&builder, &mut dot).unwrap();
+        assert_eq!(String::from_utf8(dot).unwrap(), r#"digraph circuit {
+    rankdir=LR;
+    node [shape=box];
+    c0 [label="Constant(voltage=High, resistor=false)"];
+    c2 [label="mux\nMultiplexer(select_bits=1)"];
+    c3 [label="NotGate"];
+    subgraph cluster_0 {
+        label="inv";
+        c1 [label="NotGate"];
+    }
+    w0 [shape=plaintext, label="select"];
+    w0 -> c2 [label="select"];
+    c0 -> c1 [label="data[0]"];
+    c0 -> c2 [label="data[0]"];
+    c1 -> c2 [label="data[1]"];
+    c2 -> c3 [label="out"];
+    w4 [shape=plaintext, label="w4"];
+    c3 -> w4;
+}
+"#);
+
+        let mut circuit = builder.build();
+        circuit.set_input(select, Voltage::High.into());
+        assert!(circuit.propagate(10).is_stable());
+        let mut dot = Vec::new();
+        DotExporter::new().set_voltages(true).write_circuit(&circuit, &mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("    c1 [label=\"NotGate\"];\n"));
+        assert!(dot.contains("    c2 -> c3 [label=\"out\", color=darkgreen];\n"));
+        assert!(dot.contains("    c3 -> w4 [color=green];\n"));
+    }
+}
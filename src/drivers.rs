@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use smallvec::SmallVec;
+
+use crate::{Circuit, ComponentRef, Voltage, VoltageInput, WireRef};
+use crate::circuit::{NULL_INDEX, WATCH_ERROR};
+
+// Something driving a wire, and what it is currently driving it with
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Driver {
+    // `None` for the host driving an input port via `set_input`
+    pub component: Option<ComponentRef>,
+    // Index of the component's output pin
+    pub pin: usize,
+    pub input: VoltageInput,
+}
+
+// Where a wire first went to `Voltage::Error`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorOrigin {
+    pub tick: u64,
+    pub iteration: usize,
+    // The drivers which were contributing to the wire's voltage at the time
+    pub drivers: Vec<Driver>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ErrorOrigins {
+    // For each wire, the component and pin which drive each of its input slots
+    drivers: Vec<SmallVec<[(usize, usize); 2]>>,
+    origins: HashMap<usize, ErrorOrigin>,
+}
+
+// Only the drivers which determined the voltage, so resistors are ignored
+// unless nothing else is driving the wire.
+fn contributing(drivers: Vec<Driver>) -> Vec<Driver> {
+    let driven = |driver: &Driver| driver.input.voltage != Voltage::Floating;
+    if drivers.iter().any(|driver| driven(driver) && !driver.input.resistor) {
+        drivers.into_iter().filter(|driver| driven(driver) && !driver.input.resistor).collect()
+    } else {
+        drivers.into_iter().filter(driven).collect()
+    }
+}
+
+impl Circuit {
+    fn driver_slots(&self) -> Vec<SmallVec<[(usize, usize); 2]>> {
+        let mut result: Vec<SmallVec<_>> = self.wires.iter()
+            .map(|wire| SmallVec::from_elem((NULL_INDEX, 0), wire.inputs.len()))
+            .collect();
+        for (component_id, component) in self.components.iter().enumerate() {
+            for (pin_id, pin) in component.outputs.iter().enumerate() {
+                if pin.wire_id != NULL_INDEX {
+                    result[pin.wire_id][pin.input_id] = (component_id, pin_id);
+                }
+            }
+        }
+        result
+    }
+    fn drivers_of(&self, wire_id: usize, slots: &[(usize, usize)]) -> Vec<Driver> {
+        self.wires[wire_id].inputs.iter().zip(slots).map(|(&input, &(component_id, pin))| Driver {
+            component: if component_id == NULL_INDEX { None } else { Some(ComponentRef(component_id)) },
+            pin,
+            input,
+        }).collect()
+    }
+    // Everything driving the wire, including drivers which are currently floating
+    pub fn drivers(&self, wref: WireRef) -> Vec<Driver> {
+        let mut slots = SmallVec::<[(usize, usize); 2]>::from_elem((NULL_INDEX, 0), self.wires[wref.0].inputs.len());
+        for (component_id, component) in self.components.iter().enumerate() {
+            for (pin_id, pin) in component.outputs.iter().enumerate() {
+                if pin.wire_id == wref.0 {
+                    slots[pin.input_id] = (component_id, pin_id);
+                }
+            }
+        }
+        self.drivers_of(wref.0, &slots)
+    }
+    // When enabled, the first time each wire goes to `Voltage::Error` is recorded,
+    // along with the drivers responsible. This watches every wire, so slows down
+    // propagation, and is disabled by default.
+    pub fn record_error_origins(&mut self, enabled: bool) -> &mut Self {
+        if enabled {
+            if self.error_origins.is_none() {
+                self.error_origins = Some(ErrorOrigins {
+                    drivers: self.driver_slots(),
+                    origins: HashMap::new(),
+                });
+            }
+            for wire in &mut self.wires {
+                wire.watch |= WATCH_ERROR;
+            }
+        } else {
+            self.error_origins = None;
+            for wire in &mut self.wires {
+                wire.watch &= !WATCH_ERROR;
+            }
+        }
+        self
+    }
+    pub fn error_origin(&self, wref: WireRef) -> Option<&ErrorOrigin> {
+        self.error_origins.as_ref()?.origins.get(&wref.0)
+    }
+    pub(crate) fn record_error_origin(&mut self, wire_id: usize) {
+        if let Some(error_origins) = &self.error_origins {
+            if !error_origins.origins.contains_key(&wire_id) {
+                let origin = ErrorOrigin {
+                    tick: self.change_tick(),
+                    iteration: self.iteration_count,
+                    drivers: contributing(self.drivers_of(wire_id, &error_origins.drivers[wire_id])),
+                };
+                self.error_origins.as_mut().unwrap().origins.insert(wire_id, origin);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CircuitBuilder;
+    use crate::gates::*;
+
+    #[test]
+    fn driver_conflict() {
+        let mut builder = CircuitBuilder::new();
+        let select = builder.add_input_wire();
+        let nselect = builder.add_wire();
+        let bus = builder.add_input_wire();
+        let high = builder.add_component(Constant::new(Voltage::High.into()), &[], &[nselect]);
+        let not = builder.add_component(NotGate::new(), &[select], &[nselect]);
+        let buffer = builder.add_component(ControlledBuffer::new(), &[nselect, select], &[bus]);
+        let mut circuit = builder.build();
+        circuit.record_error_origins(true);
+
+        circuit.set_input(select, Voltage::Low.into());
+        circuit.set_input(bus, VoltageInput { voltage: Voltage::Low, resistor: true });
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(bus).voltage, Voltage::Low);
+        assert_eq!(circuit.error_origin(bus), None);
+
+        circuit.tick();
+        circuit.set_input(select, Voltage::High.into());
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(nselect).voltage, Voltage::Error);
+        assert_eq!(circuit.drivers(nselect), vec![
+            Driver { component: Some(high), pin: 0, input: Voltage::High.into() },
+            Driver { component: Some(not), pin: 0, input: Voltage::Low.into() },
+        ]);
+        assert_eq!(circuit.error_origin(nselect), Some(&ErrorOrigin {
+            tick: 0,
+            iteration: 1,
+            drivers: circuit.drivers(nselect),
+        }));
+
+        // The error spreads through the buffer, but the pull-down resistor is not to blame
+        assert_eq!(circuit.wire(bus).voltage, Voltage::Error);
+        assert_eq!(circuit.drivers(bus), vec![
+            Driver { component: None, pin: 0, input: VoltageInput { voltage: Voltage::Low, resistor: true } },
+            Driver { component: Some(buffer), pin: 0, input: Voltage::Error.into() },
+        ]);
+        assert_eq!(circuit.error_origin(bus).map(|origin| &origin.drivers[..]), Some(&circuit.drivers(bus)[1..]));
+    }
+}
@@ -0,0 +1,170 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Arity, PortDirection, PropagateResult, TickStats, WireRef};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    UnknownWire(WireRef),
+    NoneInput {
+        component: String,
+        pin: usize,
+    },
+    PinCountMismatch {
+        component: String,
+        expected: Arity,
+        inputs: usize,
+        outputs: usize,
+    },
+    WidthMismatch {
+        port: String,
+        expected: usize,
+        actual: usize,
+    },
+    PortCountMismatch {
+        subcircuit: String,
+        direction: PortDirection,
+        expected: usize,
+        actual: usize,
+    },
+    DuplicateName(String),
+    InputPortConnected(String),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::UnknownWire(wire) => {
+                write!(f, "Wire #{} does not belong to this circuit", wire.0)
+            },
+            BuildError::NoneInput { component, pin } => {
+                write!(f, "Input {} of {} is connected to WireRef::NONE", pin, component)
+            },
+            BuildError::PinCountMismatch { component, expected, inputs, outputs } => write!(
+                f, "{} expects {} inputs and {} outputs, but is connected to {} inputs and {} outputs",
+                component, expected.inputs, expected.outputs, inputs, outputs
+            ),
+            BuildError::WidthMismatch { port, expected, actual } => {
+                write!(f, "Port {} has width {}, but is connected to {} wires", port, expected, actual)
+            },
+            BuildError::PortCountMismatch { subcircuit, direction, expected, actual } => write!(
+                f, "Subcircuit {} has {} {} ports, but {} were connected",
+                subcircuit, expected, direction, actual
+            ),
+            BuildError::DuplicateName(name) => write!(f, "Name `{}` is already in use", name),
+            BuildError::InputPortConnected(port) => {
+                write!(f, "Port {} is an input port driven by the host, and cannot be connected", port)
+            },
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HistoryError {
+    NotRecording,
+    OutOfRange {
+        tick: u64,
+        earliest: u64,
+        latest: u64,
+    },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::NotRecording => f.write_str("History is not being recorded"),
+            HistoryError::OutOfRange { tick, earliest, latest } => {
+                write!(f, "Cannot rewind to tick {}, history is only available for ticks {} to {}", tick, earliest, latest)
+            },
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetlistError {
+    UnregisteredComponent(String),
+    UnknownType(String),
+    InvalidParams {
+        tag: String,
+        message: String,
+    },
+    Build(BuildError),
+    // A name which refers to a wire or component that is not in the netlist
+    InvalidName(String),
+}
+
+impl From<BuildError> for NetlistError {
+    fn from(error: BuildError) -> Self {
+        NetlistError::Build(error)
+    }
+}
+
+impl fmt::Display for NetlistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetlistError::UnregisteredComponent(component) => {
+                write!(f, "Component {} has a type which is not registered", component)
+            },
+            NetlistError::UnknownType(tag) => write!(f, "Unknown component type `{}`", tag),
+            NetlistError::InvalidParams { tag, message } => {
+                write!(f, "Invalid parameters for {}: {}", tag, message)
+            },
+            NetlistError::Build(error) => error.fmt(f),
+            NetlistError::InvalidName(name) => {
+                write!(f, "Name `{}` refers to a wire or component which is not in the netlist", name)
+            },
+        }
+    }
+}
+
+impl Error for NetlistError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NetlistError::Build(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Returned by `Circuit::run` when a tick fails to settle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunError {
+    pub tick: u64,
+    pub result: Box<PropagateResult>,
+    // Statistics for the ticks which completed before the failure
+    pub stats: Vec<TickStats>,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Circuit did not settle on tick {} after {} iterations", self.tick, self.result.iterations)
+    }
+}
+
+impl Error for RunError {}
+
+// Returned when a netlist in a textual format such as BLIF cannot be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    // One-based line number, or zero for errors which are not tied to a line
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new<M: Into<String>>(line: usize, message: M) -> Self {
+        ParseError { line, message: message.into() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
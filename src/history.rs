@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::ops::RangeInclusive;
+
+use crate::{Circuit, HistoryError, Snapshot, StateReader, StateWriter, VoltageInput, WireRef};
+
+#[derive(Clone, Debug)]
+enum Event {
+    // New state of a component modified via `component_mut`, and whether it
+    // was modified via `modify_component` and so must be updated
+    Input(usize, Vec<u8>, bool),
+    SetInput(WireRef, VoltageInput),
+    Propagate(usize),
+    Tick,
+    // Ticks up to the given tick were skipped by `advance_to_next_event`
+    Skip(u64),
+}
+
+impl Event {
+    fn size(&self) -> usize {
+        mem::size_of::<Event>() + match self {
+            Event::Input(_, state, _) => state.len(),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Checkpoint {
+    snapshot: Snapshot,
+    // Index of the first event after the checkpoint was taken
+    event: u64,
+}
+
+// A journal of everything which happened to the circuit since the oldest checkpoint
+#[derive(Clone, Debug)]
+pub(crate) struct History {
+    interval: u64,
+    memory_budget: usize,
+    memory_used: usize,
+    checkpoints: VecDeque<Checkpoint>,
+    events: VecDeque<Event>,
+    first_event: u64,
+    // Components which may have been modified since the last event
+    touched: Vec<usize>,
+}
+
+impl History {
+    pub(crate) fn touch(&mut self, component_id: usize) {
+        if !self.touched.contains(&component_id) {
+            self.touched.push(component_id);
+        }
+    }
+    fn end_event(&self) -> u64 {
+        self.first_event + self.events.len() as u64
+    }
+    fn push_event(&mut self, event: Event) {
+        self.memory_used += event.size();
+        self.events.push_back(event);
+    }
+    fn push_checkpoint(&mut self, snapshot: Snapshot) {
+        self.memory_used += snapshot.size();
+        self.checkpoints.push_back(Checkpoint {
+            snapshot,
+            event: self.end_event(),
+        });
+    }
+    // Discards the oldest checkpoints until the history fits in its budget,
+    // but always keeps the most recent checkpoint.
+    fn enforce_budget(&mut self) {
+        while self.memory_used > self.memory_budget && self.checkpoints.len() > 1 {
+            let checkpoint = self.checkpoints.pop_front().unwrap();
+            self.memory_used -= checkpoint.snapshot.size();
+            let end = self.checkpoints[0].event;
+            while self.first_event < end {
+                self.memory_used -= self.events.pop_front().unwrap().size();
+                self.first_event += 1;
+            }
+        }
+    }
+    // Forgets everything after the given event
+    fn truncate(&mut self, event: u64) {
+        while self.end_event() > event {
+            self.memory_used -= self.events.pop_back().unwrap().size();
+        }
+        while matches!(self.checkpoints.back(), Some(checkpoint) if checkpoint.event > event) {
+            self.memory_used -= self.checkpoints.pop_back().unwrap().snapshot.size();
+        }
+    }
+}
+
+impl Circuit {
+    // Starts recording history, so that the circuit can be rewound to an earlier
+    // tick. A checkpoint is taken every `interval` ticks, and in between only the
+    // changes made via `component_mut` and `set_input` are recorded, so rewinding
+    // re-runs the simulation forward from the closest checkpoint. The oldest
+    // checkpoints are discarded to keep the history within `memory_budget` bytes.
+    pub fn record_history(&mut self, interval: u64, memory_budget: usize) -> &mut Self {
+        assert!(interval > 0, "Checkpoint interval must be non-zero");
+        let mut history = History {
+            interval,
+            memory_budget,
+            memory_used: 0,
+            checkpoints: VecDeque::new(),
+            events: VecDeque::new(),
+            first_event: 0,
+            touched: Vec::new(),
+        };
+        history.push_checkpoint(self.snapshot());
+        self.history = Some(history);
+        self
+    }
+    pub fn stop_recording_history(&mut self) -> &mut Self {
+        self.history = None;
+        self
+    }
+    // The ticks which the circuit can currently be rewound to
+    pub fn history_range(&self) -> Option<RangeInclusive<u64>> {
+        self.history.as_ref().map(|history| {
+            history.checkpoints[0].snapshot.tick_count()..=self.tick_count
+        })
+    }
+    fn flush_touched(&mut self) {
+        if let Some(history) = &mut self.history {
+            for component_id in mem::take(&mut history.touched) {
+                let mut state = Vec::new();
+                self.components[component_id].component_impl.save_state(&mut StateWriter::new(&mut state));
+                let dirty = self.dirty_components.contains(&component_id);
+                history.push_event(Event::Input(component_id, state, dirty));
+            }
+        }
+    }
+    pub(crate) fn record_propagate(&mut self, max_iterations: usize) {
+        self.flush_touched();
+        if let Some(history) = &mut self.history {
+            history.push_event(Event::Propagate(max_iterations));
+            history.enforce_budget();
+        }
+    }
+    // Propagation was stopped early by a breakpoint, so must be replayed the same way
+    pub(crate) fn record_pause(&mut self, iterations: usize) {
+        if let Some(history) = &mut self.history {
+            if let Some(Event::Propagate(max_iterations)) = history.events.back_mut() {
+                *max_iterations = iterations;
+            }
+        }
+    }
+    pub(crate) fn record_input(&mut self, wref: WireRef, voltage: VoltageInput) {
+        self.flush_touched();
+        if let Some(history) = &mut self.history {
+            history.push_event(Event::SetInput(wref, voltage));
+            history.enforce_budget();
+        }
+    }
+    pub(crate) fn record_skip(&mut self, tick: u64) {
+        self.flush_touched();
+        if let Some(history) = &mut self.history {
+            history.push_event(Event::Skip(tick));
+        }
+    }
+    pub(crate) fn record_tick(&mut self) {
+        self.flush_touched();
+        let due = match &self.history {
+            // Ticks may have been skipped, so the interval isn't always exact
+            Some(history) => self.tick_count >= history.checkpoints.back().unwrap().snapshot.tick_count() + history.interval,
+            None => false,
+        };
+        if due {
+            let snapshot = self.snapshot();
+            self.history.as_mut().unwrap().push_checkpoint(snapshot);
+        }
+        if let Some(history) = &mut self.history {
+            history.push_event(Event::Tick);
+            history.enforce_budget();
+        }
+    }
+    // Restores the circuit to how it was at the end of the given tick, just before
+    // `tick()` was called to advance past it. Any history after that point is
+    // discarded, and recording continues from there. Traced and observed wires
+    // are not notified of changes whilst the history is replayed.
+    pub fn rewind_to(&mut self, tick: u64) -> Result<(), HistoryError> {
+        let range = self.history_range().ok_or(HistoryError::NotRecording)?;
+        if !range.contains(&tick) {
+            return Err(HistoryError::OutOfRange {
+                tick,
+                earliest: *range.start(),
+                latest: *range.end(),
+            });
+        }
+        self.flush_touched();
+        let mut history = self.history.take().unwrap();
+
+        let checkpoint = history.checkpoints.iter().rev()
+            .find(|checkpoint| checkpoint.snapshot.tick_count() <= tick)
+            .unwrap();
+        self.restore(&checkpoint.snapshot);
+
+        let watch: Vec<u8> = self.wires.iter_mut().map(|wire| mem::replace(&mut wire.watch, 0)).collect();
+        let mut event = checkpoint.event;
+        while event < history.end_event() {
+            match &history.events[(event - history.first_event) as usize] {
+                Event::Input(component_id, state, dirty) => {
+                    self.components[*component_id].component_impl.load_state(&mut StateReader::new(state));
+                    self.reschedule(*component_id);
+                    if *dirty && !self.dirty_components.contains(component_id) {
+                        self.dirty_components.push(*component_id);
+                    }
+                },
+                Event::SetInput(wref, voltage) => self.set_input(*wref, *voltage),
+                Event::Propagate(max_iterations) => {
+                    let _ = self.propagate(*max_iterations);
+                },
+                Event::Tick if self.tick_count == tick => break,
+                Event::Tick => self.tick(),
+                Event::Skip(skip_to) if tick < *skip_to => {
+                    self.tick_count = tick;
+                    break;
+                },
+                Event::Skip(skip_to) => self.tick_count = *skip_to,
+            }
+            event += 1;
+        }
+        for (wire, watch) in self.wires.iter_mut().zip(watch) {
+            wire.watch = watch;
+        }
+
+        history.truncate(event);
+        self.history = Some(history);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CircuitBuilder, HistoryError, Voltage};
+    use crate::arithmetic::*;
+    use crate::clocks::*;
+    use crate::gates::*;
+    use crate::plexers::*;
+
+    #[test]
+    fn rewind() {
+        let mut builder = CircuitBuilder::new();
+        let enable = builder.add_wire();
+        let clk = builder.add_wire();
+        let sum = builder.add_bus(2);
+        let enable_source = builder.add_component(Constant::new(Voltage::High.into()), &[], &[enable]);
+        builder.add_component(ControlledClock::new(), &[enable], &[clk]);
+        builder.add_component(HalfAdder::new(), &[clk, enable], &sum);
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+
+        circuit.record_history(4, 1 << 20);
+        let mut states = Vec::new();
+        for i in 0..20 {
+            circuit.component_mut::<Constant>(enable_source).set((i % 3 != 0).into());
+            states.push(circuit.snapshot());
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+        }
+        assert_eq!(circuit.history_range(), Some(0..=20));
+
+        circuit.rewind_to(7).unwrap();
+        assert_eq!(circuit.snapshot(), states[7]);
+        circuit.rewind_to(2).unwrap();
+        assert_eq!(circuit.snapshot(), states[2]);
+        assert_eq!(circuit.history_range(), Some(0..=2));
+        assert_eq!(circuit.rewind_to(3), Err(HistoryError::OutOfRange {
+            tick: 3,
+            earliest: 0,
+            latest: 2,
+        }));
+
+        // Re-running forward deterministically reproduces the same states
+        for (i, state) in states.iter().enumerate().skip(2) {
+            circuit.component_mut::<Constant>(enable_source).set((i % 3 != 0).into());
+            assert_eq!(&circuit.snapshot(), state);
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+        }
+
+        // A tiny budget only keeps the most recent checkpoint
+        circuit.record_history(4, 0);
+        for _ in 0..10 {
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+        }
+        assert_eq!(circuit.history_range(), Some(28..=30));
+    }
+
+    #[test]
+    fn rewind_configuration() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_wire();
+        let out = builder.add_bus(2);
+        let clock = builder.add_component(Clock::new(), &[], &[clk]);
+        let demux = builder.add_component(Demultiplexer::new(1), &[clk, clk], &out);
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+
+        // Components are reconfigured in between checkpoints
+        circuit.record_history(4, 1 << 20);
+        let mut states = Vec::new();
+        for i in 0..12 {
+            match i {
+                2 => circuit.modify_component(demux, |demux: &mut Demultiplexer| { demux.set_three_state(true); }),
+                5 => circuit.modify_component(clock, |clock: &mut Clock| { clock.set_ticks(2, 3); }),
+                9 => circuit.modify_component(demux, |demux: &mut Demultiplexer| { demux.set_three_state(false); }),
+                _ => {},
+            }
+            assert!(circuit.propagate(10).is_stable());
+            states.push(circuit.snapshot());
+            circuit.tick();
+        }
+
+        for &tick in &[10, 6, 3, 1] {
+            circuit.rewind_to(tick).unwrap();
+            assert_eq!(circuit.snapshot(), states[tick as usize]);
+        }
+        assert!(!circuit.component_ref::<Demultiplexer>(demux).three_state());
+        assert_eq!(circuit.component_ref::<Clock>(clock).ticks_low(), 1);
+    }
+}
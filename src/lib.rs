@@ -1,16 +1,53 @@
 mod circuit;
 mod components;
+mod bus;
+mod names;
+mod subcircuit;
+mod pins;
+mod error;
+mod trace;
+mod observe;
+mod diagnostics;
+mod drivers;
+mod snapshot;
+mod history;
+mod lint;
+mod loops;
+mod breakpoints;
+mod schedule;
+mod stats;
+#[cfg(feature = "serde")]
+mod netlist;
+pub mod blif;
+pub mod dot;
+pub mod verilog;
+pub mod vcd;
 
 #[cfg(test)]
 mod test_utils;
 
 pub use self::circuit::*;
 pub use self::components::*;
+pub use self::bus::*;
+pub use self::subcircuit::*;
+pub use self::pins::*;
+pub use self::error::*;
+pub use self::trace::*;
+pub use self::observe::*;
+pub use self::diagnostics::*;
+pub use self::drivers::*;
+pub use self::snapshot::*;
+pub use self::breakpoints::*;
+pub use self::lint::*;
+pub use self::stats::*;
+#[cfg(feature = "serde")]
+pub use self::netlist::*;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::gates::*;
+    use crate::arithmetic::*;
 
     #[test]
     fn it_works() {
@@ -19,7 +56,7 @@ mod tests {
         builder.add_component(Constant::new(Voltage::High.into()), &[], &[power_line]);
     
         let mut circuit = builder.build();
-        assert!(circuit.propagate(10));
+        assert!(circuit.propagate(10).is_stable());
         assert_eq!(circuit.wire(power_line), WireState {
             voltage: Voltage::High,
             unstable: false,
@@ -39,7 +76,7 @@ mod tests {
         builder.add_component(AndGate::default(), &[power, ground], &[and_result]);
 
         let mut circuit = builder.build();
-        assert!(circuit.propagate(10));
+        assert!(circuit.propagate(10).is_stable());
         assert_eq!(circuit.wire(or_result), WireState {
             voltage: Voltage::High,
             unstable: false,
@@ -72,7 +109,7 @@ mod tests {
         builder.add_component(Buffer::default(), &[floating], &[floating_result]);
 
         let mut circuit = builder.build();
-        assert!(circuit.propagate(10));
+        assert!(circuit.propagate(10).is_stable());
         assert_eq!(circuit.wire(error_result), WireState {
             voltage: Voltage::Error,
             unstable: false,
@@ -101,10 +138,223 @@ mod tests {
             resistor: true,
         }), &[], &[pull_up]);
         builder.add_component(Constant::new(Voltage::Low.into()), &[], &[ground]);
-        builder.add_component(ControlledBuffer::default(), &[ground, pull_up], &[pull_up]);
+        let buffer = builder.add_component(ControlledBuffer::default(), &[ground, pull_up], &[pull_up]);
 
         let mut circuit = builder.build();
-        assert!(!circuit.propagate(10));
+        let result = circuit.propagate(10);
+        assert!(!result.is_stable());
+        assert_eq!(result.iterations, 10);
+        assert_eq!(result.unstable_wires, [pull_up]);
+        assert_eq!(result.last_updated, [buffer]);
+        assert_eq!(result.cycle, Some(Cycle {
+            wires: vec![pull_up],
+            components: vec![buffer],
+        }));
         assert!(circuit.wire(pull_up).unstable);
+        assert_eq!(result.oscillation, None);
+
+        let result = circuit.detect_oscillation(true).propagate(100);
+        assert!(result.iterations < 100);
+        assert_eq!(result.oscillation, Some(Oscillation {
+            period: 2,
+            wires: vec![pull_up],
+            components: vec![buffer],
+        }));
+    }
+
+    #[test]
+    fn input_ports() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.add_named_input_bus("a", 4);
+        let b = builder.add_input_bus(4);
+        let cin = builder.add_input_wire();
+        let sum = builder.add_bus(5);
+        builder.add_component(Adder::new(), &a.concat(&b).concat(&[cin]), &sum);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus(&sum).to_u64(), Some(0));
+
+        // Inputs are applied on the next propagate, without needing a tick
+        circuit.set_input_bus(&a, 9);
+        circuit.set_input_bus(&b, 5);
+        circuit.set_input(cin, Voltage::High.into());
+        assert!(circuit.wire(a[0]).unstable);
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus(&sum).to_u64(), Some(15));
+        assert_eq!(circuit.tick_count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Wire #0 is not an input port")]
+    fn set_non_input() {
+        let mut builder = CircuitBuilder::new();
+        let wire = builder.add_wire();
+        builder.build().set_input(wire, Voltage::High.into());
+    }
+
+    #[test]
+    fn modify_component() {
+        let mut builder = CircuitBuilder::new();
+        let select = builder.add_input_wire();
+        let input = builder.add_input_wire();
+        let out = builder.add_bus(2);
+        let demux = builder.add_component(plexers::Demultiplexer::new(1), &[select, input], &out);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(out[1]).voltage, Voltage::Low);
+
+        // The change is visible without ticking the circuit
+        let select_bits = circuit.modify_component(demux, |demux: &mut plexers::Demultiplexer| {
+            demux.set_three_state(true);
+            demux.select_bits()
+        });
+        assert_eq!(select_bits, 1);
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(out[1]).voltage, Voltage::Floating);
+        assert_eq!(circuit.tick_count(), 0);
+    }
+
+    #[test]
+    fn step_iteration() {
+        let mut builder = CircuitBuilder::new();
+        let input = builder.add_input_wire();
+        let inverted = builder.add_wire();
+        let output = builder.add_wire();
+        let first = builder.add_component(NotGate::new(), &[input], &[inverted]);
+        let second = builder.add_component(NotGate::new(), &[inverted], &[output]);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.pending_wires().count(), 0);
+
+        circuit.set_input(input, Voltage::High.into());
+        assert_eq!(circuit.pending_wires().collect::<Vec<_>>(), [input]);
+        let result = circuit.step_iteration();
+        assert!(!result.is_stable());
+        assert_eq!(result.last_updated, [first]);
+        assert_eq!(circuit.pending_wires().collect::<Vec<_>>(), [inverted]);
+        assert!(!circuit.step_iteration().is_stable());
+        assert_eq!(circuit.pending_wires().collect::<Vec<_>>(), [output]);
+        assert!(circuit.step_iteration().is_stable());
+        assert_eq!(circuit.wire(output).voltage, Voltage::High);
+
+        circuit.modify_component(second, |_: &mut NotGate| ());
+        assert_eq!(circuit.pending_components().collect::<Vec<_>>(), [second]);
+    }
+
+    #[test]
+    fn bus_adder() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.add_bus(4);
+        let b = builder.add_bus(4);
+        let carry = builder.add_wire();
+        let sum = builder.add_bus(5);
+        for (i, &wire) in a.iter().enumerate() {
+            builder.add_component(Constant::new(((5 >> i) & 1 == 1).into()), &[], &[wire]);
+        }
+        for (i, &wire) in b.iter().enumerate() {
+            builder.add_component(Constant::new(((7 >> i) & 1 == 1).into()), &[], &[wire]);
+        }
+        builder.add_component(Constant::new(Voltage::Low.into()), &[], &[carry]);
+        builder.add_component(Adder::new(), &a.concat(&b).concat(&[carry]), &sum);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus(&sum).to_u64(), Some(12));
+        assert_eq!(circuit.bus(&sum.slice(2..)).to_u64(), Some(3));
+        assert_eq!(circuit.wire(sum.bit(3)).voltage, Voltage::High);
+        assert_eq!(circuit.wire(sum[4]).voltage, Voltage::Low);
+    }
+
+    #[test]
+    fn named_wires() {
+        let mut builder = CircuitBuilder::new();
+        let power = builder.add_named_wire("power");
+        let data = builder.add_named_bus("data", 2);
+        let anonymous = builder.add_wire();
+        builder.add_named_component("vcc", Constant::new(Voltage::High.into()), &[], &[power]);
+        builder.add_component(Buffer::default(), &[power], &[data[0]]);
+        builder.add_component(NotGate::default(), &[power], &[data[1]]);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire_by_name("power"), Some(power));
+        assert_eq!(circuit.wire_by_name("data[1]"), Some(data[1]));
+        assert_eq!(circuit.bus_by_name("data"), Some(data.clone()));
+        assert_eq!(circuit.name_of(data[0]).as_ref().map(|s| &s[..]), Some("data[0]"));
+        assert_eq!(circuit.name_of(anonymous), None);
+        assert_eq!(circuit.component_name(circuit.component_by_name("vcc").unwrap()), Some("vcc"));
+
+        let debug = format!("{:?}", circuit);
+        assert!(debug.contains("\"data[1]\": WireState { voltage: Low"));
+        assert!(debug.contains("\"vcc\": Constant"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Component vcc is Constant")]
+    fn named_component_panic() {
+        let mut builder = CircuitBuilder::new();
+        let power = builder.add_wire();
+        let vcc = builder.add_named_component("vcc", Constant::new(Voltage::High.into()), &[], &[power]);
+
+        let circuit = builder.build();
+        circuit.component_ref::<Buffer>(vcc);
+    }
+
+    #[test]
+    fn build_errors() {
+        let mut other = CircuitBuilder::new();
+        let foreign = other.add_bus(3)[2];
+
+        let mut builder = CircuitBuilder::new();
+        let a = builder.add_wire();
+        let b = builder.add_wire();
+        assert_eq!(
+            builder.try_add_component(AndGate::default(), &[a, foreign], &[b]),
+            Err(BuildError::UnknownWire(foreign))
+        );
+        assert!(matches!(
+            builder.try_add_component(AndGate::default(), &[a, WireRef::NONE], &[b]),
+            Err(BuildError::NoneInput { pin: 1, .. })
+        ));
+        match builder.try_add_component(AndGate::default(), &[a], &[b]) {
+            Err(BuildError::PinCountMismatch { expected, inputs: 1, outputs: 1, .. }) => {
+                assert_eq!(expected, Arity::new(2, 1));
+            },
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let mut def = SubcircuitDef::new();
+        def.add_input("x", 2);
+        assert_eq!(
+            builder.try_add_subcircuit("sub", &def, &[&[a]], &[]).err(),
+            Some(BuildError::WidthMismatch { port: "sub.x".into(), expected: 2, actual: 1 })
+        );
+
+        builder.add_named_component("nand", NandGate::default(), &[a, b, a], &[b]);
+        assert_eq!(
+            builder.try_build().err().map(|e| e.to_string()),
+            Some("nand expects 2 inputs and 1 outputs, but is connected to 3 inputs and 1 outputs".into())
+        );
+    }
+
+    #[test]
+    fn pin_names() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.add_bus(2);
+        let b = builder.add_bus(2);
+        let carry = builder.add_wire();
+        let sum = builder.add_bus(3);
+        let adder = builder.add_component(Adder::new(), &a.concat(&b).concat(&[carry]), &sum);
+        assert_eq!(builder.input_names(adder), ["a[0]", "a[1]", "b[0]", "b[1]", "cin"]);
+        assert_eq!(builder.output_names(adder), ["sum[0]", "sum[1]", "cout"]);
+
+        builder.add_component(Adder::new(), &a.concat(&b), &sum);
+        assert_eq!(
+            builder.try_build().err().map(|e| e.to_string()),
+            Some("#1 expects 2n+1 inputs and n+1 outputs, but is connected to 4 inputs and 3 outputs".into())
+        );
     }
 }
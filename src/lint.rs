@@ -0,0 +1,161 @@
+use std::fmt;
+
+use crate::{CircuitBuilder, ComponentRef, Cycle, WireRef};
+use crate::circuit::NULL_INDEX;
+use crate::pins::pin_labels;
+
+// Structural problems found by `CircuitBuilder::lint`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintWarning {
+    // Components read a wire which nothing drives
+    UndrivenWire {
+        wire: WireRef,
+        name: Option<String>,
+    },
+    // An output drives a wire which is neither read by a component nor named
+    UnreadOutput {
+        component: ComponentRef,
+        name: Option<String>,
+        pin: String,
+        wire: WireRef,
+    },
+    // A wire is driven by more than one output which is not three-state
+    MultipleDrivers {
+        wire: WireRef,
+        name: Option<String>,
+        drivers: Vec<ComponentRef>,
+    },
+    // None of a component's outputs are used
+    UnusedComponent {
+        component: ComponentRef,
+        name: Option<String>,
+    },
+    // Components feed back into themselves without passing through a sequential component
+    CombinationalLoop(Cycle),
+}
+
+fn label(name: &Option<String>, index: usize) -> String {
+    name.clone().unwrap_or_else(|| format!("#{}", index))
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintWarning::UndrivenWire { wire, name } => {
+                write!(f, "Wire {} is read, but nothing drives it", label(name, wire.0))
+            },
+            LintWarning::UnreadOutput { component, name, pin, wire } => write!(
+                f, "Output {} of {} drives wire #{}, which is never read",
+                pin, label(name, component.0), wire.0
+            ),
+            LintWarning::MultipleDrivers { wire, name, drivers } => write!(
+                f, "Wire {} is driven by {} outputs which are not three-state",
+                label(name, wire.0), drivers.len()
+            ),
+            LintWarning::UnusedComponent { component, name } => {
+                write!(f, "None of the outputs of {} are used", label(name, component.0))
+            },
+            LintWarning::CombinationalLoop(cycle) => {
+                write!(f, "Combinational loop through {} components", cycle.components.len())
+            },
+        }
+    }
+}
+
+impl CircuitBuilder {
+    // Looks for mistakes which do not prevent the circuit from being built, but which
+    // are probably bugs. Named wires are assumed to be read by the host, and components
+    // which do not describe themselves are assumed not to have three-state outputs.
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut result = Vec::new();
+        let is_read = |wire_id: usize| {
+            !self.wires[wire_id].components.is_empty() || self.names.primary_name(WireRef(wire_id)).is_some()
+        };
+
+        let mut drivers = vec![Vec::new(); self.wires.len()];
+        for (i, component) in self.components.iter().enumerate() {
+            let cref = ComponentRef(i);
+            let (num_inputs, num_outputs) = (component.inputs.len(), component.outputs.len());
+            let three_state = component.component_impl.describe()
+                .map(|description| description.three_state_outputs(num_inputs, num_outputs))
+                .unwrap_or_else(|| vec![false; num_outputs]);
+            for (pin, three_state) in component.outputs.iter().zip(three_state) {
+                if pin.wire_id != NULL_INDEX && !three_state && !drivers[pin.wire_id].contains(&cref) {
+                    drivers[pin.wire_id].push(cref);
+                }
+            }
+
+            let unread: Vec<usize> = (0..num_outputs).filter(|&pin| {
+                let wire_id = component.outputs[pin].wire_id;
+                wire_id == NULL_INDEX || !is_read(wire_id)
+            }).collect();
+            let name = self.names.component_name(cref).map(String::from);
+            if num_outputs > 0 && unread.len() == num_outputs {
+                result.push(LintWarning::UnusedComponent { component: cref, name });
+            } else if !unread.is_empty() {
+                let pins = pin_labels(&*component.component_impl, num_inputs, num_outputs).1;
+                // Outputs deliberately connected to `WireRef::NONE` are fine
+                for pin in unread {
+                    let wire_id = component.outputs[pin].wire_id;
+                    if wire_id != NULL_INDEX {
+                        result.push(LintWarning::UnreadOutput {
+                            component: cref,
+                            name: name.clone(),
+                            pin: pins[pin].clone(),
+                            wire: WireRef(wire_id),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (i, (wire, drivers)) in self.wires.iter().zip(drivers).enumerate() {
+            let name = self.names.name_of(WireRef(i));
+            if wire.num_inputs == 0 && !wire.components.is_empty() {
+                result.push(LintWarning::UndrivenWire { wire: WireRef(i), name: name.clone() });
+            }
+            if drivers.len() > 1 {
+                result.push(LintWarning::MultipleDrivers { wire: WireRef(i), name, drivers });
+            }
+        }
+        result.extend(self.combinational_loops().into_iter().map(LintWarning::CombinationalLoop));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Voltage, VoltageInput};
+    use crate::arithmetic::*;
+    use crate::gates::*;
+
+    #[test]
+    fn lint() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.add_named_input_wire("a");
+        let b = builder.add_named_wire("b");
+        let bus = builder.add_named_wire("bus");
+        let out = builder.add_named_wire("out");
+        let unread = builder.add_wire();
+        let dangling = builder.add_wire();
+        let and = builder.add_named_component("and", AndGate::new(), &[a, b], &[out]);
+        let high = builder.add_component(Constant::new(Voltage::High.into()), &[], &[bus]);
+        let not = builder.add_component(NotGate::new(), &[a], &[bus]);
+        builder.add_component(Constant::new(VoltageInput { voltage: Voltage::Low, resistor: true }), &[], &[bus]);
+        builder.add_component(ControlledBuffer::new(), &[a, a], &[bus]);
+        let nor = builder.add_component(NorGate::new(), &[a, bus], &[dangling]);
+        let adder = builder.add_named_component("adder", HalfAdder::new(), &[a, bus], &[out, unread]);
+
+        let warnings = builder.lint();
+        assert_eq!(warnings, vec![
+            LintWarning::UnusedComponent { component: nor, name: None },
+            LintWarning::UnreadOutput { component: adder, name: Some("adder".into()), pin: "carry".into(), wire: unread },
+            LintWarning::UndrivenWire { wire: b, name: Some("b".into()) },
+            LintWarning::MultipleDrivers { wire: bus, name: Some("bus".into()), drivers: vec![high, not] },
+            LintWarning::MultipleDrivers { wire: out, name: Some("out".into()), drivers: vec![and, adder] },
+        ]);
+        assert_eq!(warnings[1].to_string(), "Output carry of adder drives wire #4, which is never read");
+        assert_eq!(warnings[2].to_string(), "Wire b is read, but nothing drives it");
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+
+use crate::{CircuitBuilder, ComponentRef, Cycle, WireRef};
+use crate::circuit::NULL_INDEX;
+
+impl CircuitBuilder {
+    // For each component, the components which read its outputs and the wires they
+    // read them from. Sequential components are left out, as they break loops.
+    fn combinational_edges(&self) -> Vec<Vec<(usize, usize)>> {
+        let sequential: Vec<bool> = self.components.iter()
+            .map(|component| component.component_impl.is_sequential())
+            .collect();
+        self.components.iter().enumerate().map(|(i, component)| {
+            if sequential[i] {
+                return Vec::new();
+            }
+            let mut edges = Vec::new();
+            for pin in &component.outputs {
+                if pin.wire_id == NULL_INDEX {
+                    continue;
+                }
+                for &reader in &self.wires[pin.wire_id].components {
+                    if !sequential[reader] {
+                        edges.push((pin.wire_id, reader));
+                    }
+                }
+            }
+            edges
+        }).collect()
+    }
+    // Finds the groups of components which feed back into themselves without passing
+    // through a sequential component, using Tarjan's algorithm. One loop is reported
+    // for each group, starting from its lowest numbered component.
+    pub fn combinational_loops(&self) -> Vec<Cycle> {
+        let edges = self.combinational_edges();
+        let count = edges.len();
+        let mut index = vec![NULL_INDEX; count];
+        let mut low_link = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = Vec::new();
+        let mut groups = Vec::new();
+        let mut next_index = 0;
+
+        for start in 0..count {
+            if index[start] != NULL_INDEX {
+                continue;
+            }
+            // Stack of (component, next edge to follow)
+            let mut call_stack = vec![(start, 0)];
+            index[start] = next_index;
+            low_link[start] = next_index;
+            next_index += 1;
+            stack.push(start);
+            on_stack[start] = true;
+
+            while let Some(&mut (node, ref mut edge)) = call_stack.last_mut() {
+                if let Some(&(_, successor)) = edges[node].get(*edge) {
+                    *edge += 1;
+                    if index[successor] == NULL_INDEX {
+                        index[successor] = next_index;
+                        low_link[successor] = next_index;
+                        next_index += 1;
+                        stack.push(successor);
+                        on_stack[successor] = true;
+                        call_stack.push((successor, 0));
+                    } else if on_stack[successor] {
+                        low_link[node] = low_link[node].min(index[successor]);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    low_link[parent] = low_link[parent].min(low_link[node]);
+                }
+                if low_link[node] == index[node] {
+                    let mut group = Vec::new();
+                    loop {
+                        let member = stack.pop().unwrap();
+                        on_stack[member] = false;
+                        group.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    let feedback = group.len() > 1 || edges[node].iter().any(|&(_, successor)| successor == node);
+                    if feedback {
+                        groups.push(group);
+                    }
+                }
+            }
+        }
+
+        let mut result: Vec<Cycle> = groups.into_iter().map(|mut group| {
+            group.sort_unstable();
+            loop_through(&edges, &group)
+        }).collect();
+        result.sort_by_key(|cycle| cycle.components[0].0);
+        result
+    }
+}
+
+// The shortest loop from the first component of a group back to itself,
+// only passing through members of the group.
+fn loop_through(edges: &[Vec<(usize, usize)>], group: &[usize]) -> Cycle {
+    let start = group[0];
+    let mut previous = vec![None; edges.len()];
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        for &(wire_id, successor) in &edges[node] {
+            if successor == start {
+                // Walk back to the start to recover the loop
+                let mut cycle = Cycle::default();
+                let mut wire = wire_id;
+                let mut current = node;
+                loop {
+                    cycle.components.push(ComponentRef(current));
+                    cycle.wires.push(WireRef(wire));
+                    match previous[current] {
+                        Some((previous_wire, previous_node)) if current != start => {
+                            wire = previous_wire;
+                            current = previous_node;
+                        },
+                        _ => break,
+                    }
+                }
+                cycle.components.reverse();
+                cycle.wires.reverse();
+                return cycle;
+            }
+            if previous[successor].is_none() && successor != start && group.binary_search(&successor).is_ok() {
+                previous[successor] = Some((wire_id, node));
+                queue.push_back(successor);
+            }
+        }
+    }
+    unreachable!("Strongly connected group has no loop")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::*;
+    use crate::latches::*;
+
+    #[test]
+    fn combinational_loops() {
+        let mut builder = CircuitBuilder::new();
+        let s = builder.add_input_wire();
+        let r = builder.add_input_wire();
+        let q = builder.add_wire();
+        let nq = builder.add_wire();
+        let ring = builder.add_bus(3);
+        let latched = builder.add_bus(2);
+
+        // A latch built from gates is a loop, but the latch component is not
+        let nor_a = builder.add_component(NorGate::new(), &[r, nq], &[q]);
+        let nor_b = builder.add_component(NorGate::new(), &[s, q], &[nq]);
+        builder.add_component(SrNorLatch::new(), &[s, latched[1]], &latched);
+        builder.add_component(Buffer::new(), &[latched[0]], &[latched[1]]);
+
+        // A ring oscillator, with a buffer hanging off it
+        let not_a = builder.add_component(NotGate::new(), &[ring[2]], &[ring[0]]);
+        let not_b = builder.add_component(NotGate::new(), &[ring[0]], &[ring[1]]);
+        let not_c = builder.add_component(NotGate::new(), &[ring[1]], &[ring[2]]);
+        builder.add_component(Buffer::new(), &[ring[1]], &[WireRef::NONE]);
+
+        assert_eq!(builder.combinational_loops(), vec![
+            Cycle { wires: vec![q, nq], components: vec![nor_a, nor_b] },
+            Cycle { wires: vec![ring[0], ring[1], ring[2]], components: vec![not_a, not_b, not_c] },
+        ]);
+    }
+}
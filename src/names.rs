@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::{BusRef, WireRef, ComponentRef};
+use crate::circuit::NULL_INDEX;
+
+#[derive(Clone, Debug)]
+struct Instance {
+    path: String,
+    components: Range<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Names {
+    buses: BTreeMap<String, BusRef>,
+    components: BTreeMap<String, ComponentRef>,
+    instances: Vec<Instance>,
+    // Reverse lookups, the first name given to a wire or component wins
+    wire_names: BTreeMap<usize, (String, Option<usize>)>,
+    component_names: BTreeMap<usize, String>,
+}
+
+// Splits `foo[3]` into `("foo", 3)`
+fn split_index(path: &str) -> Option<(&str, usize)> {
+    if !path.ends_with(']') {
+        return None;
+    }
+    let open = path.rfind('[')?;
+    let index = path[open+1..path.len()-1].parse().ok()?;
+    Some((&path[..open], index))
+}
+
+impl Names {
+    pub(crate) fn add_bus(&mut self, name: &str, bus: BusRef) {
+        assert!(!self.buses.contains_key(name), "Duplicate wire name `{}`", name);
+        for (i, wire) in bus.iter().enumerate() {
+            if wire.0 != NULL_INDEX {
+                let bit = if bus.width() == 1 { None } else { Some(i) };
+                self.wire_names.entry(wire.0).or_insert_with(|| (name.into(), bit));
+            }
+        }
+        self.buses.insert(name.into(), bus);
+    }
+    pub(crate) fn add_component(&mut self, name: &str, cref: ComponentRef) {
+        assert!(!self.components.contains_key(name), "Duplicate component name `{}`", name);
+        self.component_names.entry(cref.0).or_insert_with(|| name.into());
+        self.components.insert(name.into(), cref);
+    }
+    pub(crate) fn add_instance(&mut self, path: &str, components: Range<usize>) {
+        self.instances.push(Instance { path: path.into(), components });
+    }
+    pub(crate) fn bus(&self, path: &str) -> Option<BusRef> {
+        if let Some(bus) = self.buses.get(path) {
+            Some(bus.clone())
+        } else {
+            let (base, index) = split_index(path)?;
+            let bus = self.buses.get(base)?;
+            bus.get(index).map(|&wire| wire.into())
+        }
+    }
+    pub(crate) fn wire(&self, path: &str) -> Option<WireRef> {
+        let bus = self.bus(path)?;
+        if bus.width() == 1 {
+            Some(bus.bit(0))
+        } else {
+            None
+        }
+    }
+    pub(crate) fn component(&self, path: &str) -> Option<ComponentRef> {
+        self.components.get(path).cloned()
+    }
+    pub(crate) fn buses(&self) -> impl Iterator<Item = (&str, &BusRef)> {
+        self.buses.iter().map(|(name, bus)| (&name[..], bus))
+    }
+    #[cfg(feature = "serde")]
+    pub(crate) fn components(&self) -> impl Iterator<Item = (&str, ComponentRef)> {
+        self.components.iter().map(|(name, &cref)| (&name[..], cref))
+    }
+    #[cfg(feature = "serde")]
+    pub(crate) fn instances(&self) -> impl Iterator<Item = (&str, Range<usize>)> {
+        self.instances.iter().map(|instance| (&instance.path[..], instance.components.clone()))
+    }
+    pub(crate) fn primary_name(&self, wref: WireRef) -> Option<(&str, Option<usize>)> {
+        self.wire_names.get(&wref.0).map(|(name, bit)| (&name[..], *bit))
+    }
+    pub(crate) fn name_of(&self, wref: WireRef) -> Option<String> {
+        self.wire_names.get(&wref.0).map(|(name, bit)| match bit {
+            Some(bit) => format!("{}[{}]", name, bit),
+            None => name.clone(),
+        })
+    }
+    pub(crate) fn component_name(&self, cref: ComponentRef) -> Option<&str> {
+        self.component_names.get(&cref.0).map(|name| &name[..])
+    }
+    // Human readable descriptions for use in messages, falling back to the index
+    pub(crate) fn wire_label(&self, wref: WireRef) -> String {
+        self.name_of(wref).unwrap_or_else(|| format!("#{}", wref.0))
+    }
+    pub(crate) fn component_label(&self, cref: ComponentRef) -> String {
+        match (self.component_name(cref), self.scope_of(cref)) {
+            (Some(name), _) => name.into(),
+            (None, Some(scope)) => format!("{}.#{}", scope, cref.0),
+            (None, None) => format!("#{}", cref.0),
+        }
+    }
+    pub(crate) fn instance_paths(&self) -> impl Iterator<Item = &str> {
+        self.instances.iter().map(|instance| &instance.path[..])
+    }
+    pub(crate) fn scope_of(&self, cref: ComponentRef) -> Option<&str> {
+        // Nested instances are always recorded after their parent
+        self.instances.iter().rev()
+            .find(|instance| instance.components.contains(&cref.0))
+            .map(|instance| &instance.path[..])
+    }
+    // The first name which `merge` would add that is already taken, if any
+    pub(crate) fn merge_conflict(&self, other: &Names, prefix: &str) -> Option<String> {
+        if self.instances.iter().any(|instance| instance.path == prefix) {
+            return Some(prefix.into());
+        }
+        let buses = other.buses.keys().map(|name| format!("{}.{}", prefix, name))
+            .find(|name| self.buses.contains_key(name));
+        buses.or_else(|| other.components.keys().map(|name| format!("{}.{}", prefix, name))
+            .find(|name| self.components.contains_key(name)))
+    }
+    // Copies the names from a subcircuit definition into this table
+    pub(crate) fn merge(&mut self, other: &Names, prefix: &str, wire_map: &[usize], first_component: usize) {
+        for (name, bus) in &other.buses {
+            let bus = bus.iter()
+                .map(|wire| WireRef(if wire.0 == NULL_INDEX { NULL_INDEX } else { wire_map[wire.0] }))
+                .collect();
+            self.add_bus(&format!("{}.{}", prefix, name), bus);
+        }
+        for (name, cref) in &other.components {
+            self.add_component(&format!("{}.{}", prefix, name), ComponentRef(cref.0 + first_component));
+        }
+        for instance in &other.instances {
+            self.add_instance(
+                &format!("{}.{}", prefix, instance.path),
+                (instance.components.start + first_component)..(instance.components.end + first_component),
+            );
+        }
+    }
+}
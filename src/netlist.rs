@@ -0,0 +1,416 @@
+// Saving and loading circuit designs, requires the `serde` feature
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{AnyComponent, BuildError, BusRef, CircuitBuilder, ComponentRef, NetlistError, WireRef};
+use crate::arithmetic::*;
+use crate::clocks::*;
+use crate::gates::*;
+use crate::latches::*;
+use crate::memory::*;
+use crate::plexers::*;
+
+type SaveFn = fn(&dyn Any) -> serde_json::Result<Value>;
+type LoadFn = fn(Value) -> serde_json::Result<Box<dyn AnyComponent>>;
+type CheckFn = fn(&dyn Any) -> Result<(), String>;
+
+// Checks for parameters which would make a component panic, rather than fail to
+// deserialize. Components registered with `register_checked` are checked on load.
+pub trait CheckParams {
+    fn check_params(&self) -> Result<(), String>;
+}
+
+fn check_select_bits(select_bits: usize) -> Result<(), String> {
+    if select_bits < usize::BITS as usize {
+        Ok(())
+    } else {
+        Err(format!("select_bits must be less than {}", usize::BITS))
+    }
+}
+
+fn check_clock(ticks_low: u32, ticks_high: u32, tick_phase: u32) -> Result<(), String> {
+    match ticks_low.checked_add(ticks_high) {
+        Some(0) => Err("ticks_low and ticks_high cannot both be zero".into()),
+        Some(period) if period.checked_add(tick_phase).is_some() => Ok(()),
+        _ => Err("ticks_low, ticks_high and tick_phase are too large".into()),
+    }
+}
+
+impl CheckParams for Multiplexer {
+    fn check_params(&self) -> Result<(), String> { check_select_bits(self.select_bits() as usize) }
+}
+
+impl CheckParams for Demultiplexer {
+    fn check_params(&self) -> Result<(), String> { check_select_bits(self.select_bits() as usize) }
+}
+
+impl CheckParams for PriorityEncoder {
+    fn check_params(&self) -> Result<(), String> { check_select_bits(self.select_bits() as usize) }
+}
+
+impl CheckParams for Shifter {
+    fn check_params(&self) -> Result<(), String> { check_select_bits(self.select_bits()) }
+}
+
+impl CheckParams for Clock {
+    fn check_params(&self) -> Result<(), String> { check_clock(self.ticks_low(), self.ticks_high(), self.tick_phase()) }
+}
+
+impl CheckParams for ControlledClock {
+    fn check_params(&self) -> Result<(), String> { check_clock(self.ticks_low(), self.ticks_high(), self.tick_phase()) }
+}
+
+impl CheckParams for LookupTable {
+    fn check_params(&self) -> Result<(), String> {
+        if self.rows().iter().all(|row| row.len() == self.inputs()) {
+            Ok(())
+        } else {
+            Err("rows must have one entry per input".into())
+        }
+    }
+}
+
+fn check<C: CheckParams + 'static>(component: &dyn Any) -> Result<(), String> {
+    component.downcast_ref::<C>().unwrap().check_params()
+}
+
+fn no_check(_component: &dyn Any) -> Result<(), String> {
+    Ok(())
+}
+
+fn save<C: AnyComponent + Serialize>(component: &dyn Any) -> serde_json::Result<Value> {
+    serde_json::to_value(component.downcast_ref::<C>().unwrap())
+}
+
+fn load<C: AnyComponent + DeserializeOwned>(params: Value) -> serde_json::Result<Box<dyn AnyComponent>> {
+    Ok(Box::new(serde_json::from_value::<C>(params)?))
+}
+
+struct Entry {
+    tag: String,
+    save: SaveFn,
+    load: LoadFn,
+    check: CheckFn,
+}
+
+// Maps component types to and from the type tags used in a netlist. User-defined
+// components must be registered before a circuit containing them can be saved.
+pub struct ComponentRegistry {
+    entries: Vec<Entry>,
+    by_type: HashMap<TypeId, usize>,
+    by_tag: HashMap<String, usize>,
+}
+
+impl ComponentRegistry {
+    // An empty registry, use `default()` to start with the built-in components
+    pub fn new() -> Self {
+        ComponentRegistry {
+            entries: Vec::new(),
+            by_type: HashMap::new(),
+            by_tag: HashMap::new(),
+        }
+    }
+    pub fn register<C: AnyComponent + Serialize + DeserializeOwned>(&mut self, tag: &str) -> &mut Self {
+        self.add_entry::<C>(tag, no_check)
+    }
+    pub fn register_checked<C: AnyComponent + Serialize + DeserializeOwned + CheckParams>(&mut self, tag: &str) -> &mut Self {
+        self.add_entry::<C>(tag, check::<C>)
+    }
+    fn add_entry<C: AnyComponent + Serialize + DeserializeOwned>(&mut self, tag: &str, check: CheckFn) -> &mut Self {
+        assert!(!self.by_tag.contains_key(tag), "Duplicate component type `{}`", tag);
+        let index = self.entries.len();
+        self.entries.push(Entry {
+            tag: tag.into(),
+            save: save::<C>,
+            load: load::<C>,
+            check,
+        });
+        self.by_type.insert(TypeId::of::<C>(), index);
+        self.by_tag.insert(tag.into(), index);
+        self
+    }
+    pub fn tag_of(&self, component: &dyn AnyComponent) -> Option<&str> {
+        let index = *self.by_type.get(&component.as_any_ref().type_id())?;
+        Some(&self.entries[index].tag)
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        let mut result = Self::new();
+        result
+            .register::<Constant>("Constant")
+            .register::<Buffer>("Buffer")
+            .register::<NotGate>("NotGate")
+            .register::<AndGate>("AndGate")
+            .register::<OrGate>("OrGate")
+            .register::<XorGate>("XorGate")
+            .register::<NandGate>("NandGate")
+            .register::<NorGate>("NorGate")
+            .register::<XnorGate>("XnorGate")
+            .register::<ImplyGate>("ImplyGate")
+            .register::<ControlledBuffer>("ControlledBuffer")
+            .register::<ControlledInverter>("ControlledInverter")
+            .register::<NaryAndGate>("NaryAndGate")
+            .register::<NaryOrGate>("NaryOrGate")
+            .register::<ParityGate>("ParityGate")
+            .register_checked::<LookupTable>("LookupTable")
+            .register::<SrNorLatch>("SrNorLatch")
+            .register::<DFlipFlop>("DFlipFlop")
+            .register::<DLatch>("DLatch")
+            .register_checked::<Clock>("Clock")
+            .register_checked::<ControlledClock>("ControlledClock")
+            .register_checked::<Multiplexer>("Multiplexer")
+            .register_checked::<Demultiplexer>("Demultiplexer")
+            .register_checked::<PriorityEncoder>("PriorityEncoder")
+            .register::<BitAdder>("BitAdder")
+            .register::<HalfAdder>("HalfAdder")
+            .register::<FullAdder>("FullAdder")
+            .register::<Adder>("Adder")
+            .register::<Subtractor>("Subtractor")
+            .register::<Multiplier>("Multiplier")
+            .register::<Negator>("Negator")
+            .register::<Comparator>("Comparator")
+            .register_checked::<Shifter>("Shifter");
+        result
+    }
+}
+
+impl fmt::Debug for ComponentRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.entries.iter().map(|entry| &entry.tag)).finish()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetlistComponent {
+    #[serde(rename = "type")]
+    pub tag: String,
+    #[serde(default)]
+    pub params: Value,
+    pub inputs: Vec<WireRef>,
+    pub outputs: Vec<WireRef>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetlistInstance {
+    pub path: String,
+    // Indices of the components which belong to the instance
+    pub components: Range<usize>,
+}
+
+// Only the names themselves are saved, the reverse lookups are rebuilt when loading
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetlistNames {
+    #[serde(default)]
+    pub buses: BTreeMap<String, BusRef>,
+    #[serde(default)]
+    pub components: BTreeMap<String, ComponentRef>,
+    #[serde(default)]
+    pub instances: Vec<NetlistInstance>,
+}
+
+// A serializable form of `CircuitBuilder`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Netlist {
+    pub wires: usize,
+    #[serde(default)]
+    pub inputs: Vec<WireRef>,
+    pub components: Vec<NetlistComponent>,
+    #[serde(default)]
+    pub names: NetlistNames,
+}
+
+impl CircuitBuilder {
+    pub fn to_netlist(&self, registry: &ComponentRegistry) -> Result<Netlist, NetlistError> {
+        let components = self.components.iter().enumerate().map(|(i, component)| {
+            let index = *registry.by_type.get(&component.component_impl.as_any_ref().type_id())
+                .ok_or_else(|| NetlistError::UnregisteredComponent(self.names.component_label(ComponentRef(i))))?;
+            let entry = &registry.entries[index];
+            Ok(NetlistComponent {
+                tag: entry.tag.clone(),
+                params: (entry.save)(component.component_impl.as_any_ref()).map_err(|e| NetlistError::InvalidParams {
+                    tag: entry.tag.clone(),
+                    message: e.to_string(),
+                })?,
+                inputs: component.inputs.iter().map(|&wire_id| WireRef(wire_id)).collect(),
+                outputs: component.outputs.iter().map(|pin| WireRef(pin.wire_id)).collect(),
+            })
+        }).collect::<Result<_, NetlistError>>()?;
+
+        Ok(Netlist {
+            wires: self.wires.len(),
+            inputs: (0..self.wires.len()).filter(|&i| self.wires[i].external).map(WireRef).collect(),
+            components,
+            names: NetlistNames {
+                buses: self.names.buses().map(|(name, bus)| (name.into(), bus.clone())).collect(),
+                components: self.names.components().map(|(name, cref)| (name.into(), cref)).collect(),
+                instances: self.names.instances().map(|(path, components)| NetlistInstance {
+                    path: path.into(),
+                    components,
+                }).collect(),
+            },
+        })
+    }
+    pub fn from_netlist(netlist: &Netlist, registry: &ComponentRegistry) -> Result<Self, NetlistError> {
+        let mut result = CircuitBuilder::new();
+        result.add_bus(netlist.wires);
+        for &input in &netlist.inputs {
+            // Input ports must claim their wire's first input slot before any components do
+            let wire = result.wires.get_mut(input.0).ok_or(BuildError::UnknownWire(input))?;
+            if !wire.external {
+                wire.external = true;
+                wire.add_input();
+            }
+        }
+        for component in &netlist.components {
+            let index = *registry.by_tag.get(&component.tag)
+                .ok_or_else(|| NetlistError::UnknownType(component.tag.clone()))?;
+            let entry = &registry.entries[index];
+            let component_impl = (entry.load)(component.params.clone()).map_err(|e| e.to_string())
+                .and_then(|component_impl| (entry.check)(component_impl.as_any_ref()).map(|_| component_impl))
+                .map_err(|message| NetlistError::InvalidParams {
+                    tag: component.tag.clone(),
+                    message,
+                })?;
+            result.try_add_boxed_component(component_impl, &component.inputs, &component.outputs)?;
+        }
+        let num_components = netlist.components.len();
+        for (name, bus) in &netlist.names.buses {
+            if !bus.iter().all(|wire| wire.0 < netlist.wires || *wire == WireRef::NONE) {
+                return Err(NetlistError::InvalidName(name.clone()));
+            }
+            result.name_bus(bus, name);
+        }
+        for (name, &cref) in &netlist.names.components {
+            if cref.0 >= num_components {
+                return Err(NetlistError::InvalidName(name.clone()));
+            }
+            result.name_component(cref, name);
+        }
+        for instance in &netlist.names.instances {
+            let components = &instance.components;
+            if components.start > components.end || components.end > num_components {
+                return Err(NetlistError::InvalidName(instance.path.clone()));
+            }
+            result.names.add_instance(&instance.path, components.clone());
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Component, ComponentInterface, Voltage};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Pulse {
+        width: u32,
+    }
+
+    impl Component for Pulse {
+        fn update(&mut self, interface: &mut ComponentInterface) {
+            interface.output(0, (self.width > 0).into());
+        }
+    }
+
+    impl CheckParams for Pulse {
+        fn check_params(&self) -> Result<(), String> {
+            if self.width <= 100 { Ok(()) } else { Err("width must be at most 100".into()) }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_named_wire("clk");
+        let select = builder.add_named_bus("select", 2);
+        let enable = builder.add_named_input_wire("enable");
+        let out = builder.add_named_bus("out", 4);
+        let pulse = builder.add_wire();
+        let mut clock = Clock::new();
+        clock.set_ticks(2, 3).set_phase(1);
+        builder.add_named_component("clock", clock, &[], &[clk]);
+        builder.add_component(Constant::new(Voltage::High.into()), &[], &[select[0]]);
+        builder.add_component(NotGate::new(), &[select[0]], &[select[1]]);
+        let mut demux = Demultiplexer::new(2);
+        demux.set_three_state(true);
+        builder.add_component(demux, &[select[0], select[1], enable], &out);
+        builder.add_component(Pulse { width: 3 }, &[], &[pulse]);
+
+        assert_eq!(builder.to_netlist(&ComponentRegistry::default()).unwrap_err(), NetlistError::UnregisteredComponent("#4".into()));
+
+        let mut registry = ComponentRegistry::default();
+        registry.register::<Pulse>("Pulse");
+        let json = serde_json::to_string(&builder.to_netlist(&registry).unwrap()).unwrap();
+        let netlist: Netlist = serde_json::from_str(&json).unwrap();
+        assert_eq!(netlist.components[0].params, serde_json::json!({
+            "ticks_low": 2,
+            "ticks_high": 3,
+            "tick_phase": 1,
+        }));
+        assert_eq!(netlist.components[3].params, serde_json::json!({
+            "select_bits": 2,
+            "three_state": true,
+        }));
+
+        let loaded = CircuitBuilder::from_netlist(&netlist, &registry).unwrap();
+        assert_eq!(serde_json::to_string(&loaded.to_netlist(&registry).unwrap()).unwrap(), json);
+
+        let clock = loaded.component_by_name("clock").unwrap();
+        // Only the names are saved, and the reverse lookups are rebuilt from them
+        assert!(!json.contains("wire_names"));
+        assert_eq!(loaded.name_of(out[1]), Some("out[1]".into()));
+        assert_eq!(loaded.component_name(clock), Some("clock"));
+        let mut circuit = loaded.build();
+        assert_eq!(circuit.component_ref::<Clock>(clock).tick_phase(), 1);
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus_by_name("out").map(|bus| circuit.bus(&bus).voltages.to_vec()), Some(vec![
+            Voltage::Floating, Voltage::Low, Voltage::Floating, Voltage::Floating,
+        ]));
+        circuit.set_input(enable, Voltage::High.into());
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(out[1]).voltage, Voltage::High);
+    }
+
+    #[test]
+    fn invalid_netlists() {
+        let mut registry = ComponentRegistry::default();
+        registry.register_checked::<Pulse>("Pulse");
+        let load = |json: serde_json::Value| {
+            CircuitBuilder::from_netlist(&serde_json::from_value(json).unwrap(), &registry).err().map(|e| e.to_string())
+        };
+        assert_eq!(load(serde_json::json!({
+            "wires": 1,
+            "components": [{"type": "Multiplexer", "params": {"select_bits": 70}, "inputs": [], "outputs": [0]}],
+        })), Some(format!("Invalid parameters for Multiplexer: select_bits must be less than {}", usize::BITS)));
+        assert_eq!(load(serde_json::json!({
+            "wires": 1,
+            "components": [{"type": "Clock", "params": {"ticks_low": 0, "ticks_high": 0}, "inputs": [], "outputs": [0]}],
+        })), Some("Invalid parameters for Clock: ticks_low and ticks_high cannot both be zero".into()));
+        assert_eq!(load(serde_json::json!({
+            "wires": 1,
+            "components": [{"type": "LookupTable", "params": {"inputs": 1, "rows": [[true, null]], "value": true}, "inputs": [0], "outputs": [0]}],
+        })), Some("Invalid parameters for LookupTable: rows must have one entry per input".into()));
+        assert_eq!(load(serde_json::json!({
+            "wires": 1,
+            "components": [{"type": "Pulse", "params": {"width": 500}, "inputs": [], "outputs": [0]}],
+        })), Some("Invalid parameters for Pulse: width must be at most 100".into()));
+        assert_eq!(load(serde_json::json!({
+            "wires": 1,
+            "components": [],
+            "names": {
+                "buses": {"a": [0, 3]},
+                "components": {},
+                "instances": [],
+            },
+        })), Some("Name `a` refers to a wire or component which is not in the netlist".into()));
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use crate::{Circuit, WireChange, WireRef};
+use crate::circuit::WATCH_OBSERVE;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverRef(usize);
+
+type Callback = Box<dyn FnMut(&WireChange)>;
+
+// Callbacks cannot be cloned, so a cloned circuit starts with no observers
+#[derive(Default)]
+pub(crate) struct Observers {
+    callbacks: Vec<Option<Callback>>,
+    by_wire: HashMap<usize, SmallVec<[usize; 2]>>,
+}
+
+impl Observers {
+    pub(crate) fn notify(&mut self, change: &WireChange) {
+        if let Some(ids) = self.by_wire.get(&change.wire.0) {
+            for &id in ids {
+                if let Some(callback) = &mut self.callbacks[id] {
+                    callback(change);
+                }
+            }
+        }
+    }
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Default::default()
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Observers({})", self.callbacks.iter().filter(|c| c.is_some()).count())
+    }
+}
+
+impl Circuit {
+    // The callback is invoked from within `propagate`, as soon as a watched wire changes voltage
+    pub fn add_observer<F: FnMut(&WireChange) + 'static>(&mut self, wires: &[WireRef], callback: F) -> ObserverRef {
+        let id = self.observers.callbacks.len();
+        self.observers.callbacks.push(Some(Box::new(callback)));
+        for wire in wires {
+            let ids = self.observers.by_wire.entry(wire.0).or_default();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+            self.wires[wire.0].watch |= WATCH_OBSERVE;
+        }
+        ObserverRef(id)
+    }
+    pub fn remove_observer(&mut self, observer: ObserverRef) {
+        self.observers.callbacks[observer.0] = None;
+        let wires = &mut self.wires;
+        self.observers.by_wire.retain(|&wire_id, ids| {
+            ids.retain(|id| *id != observer.0);
+            if ids.is_empty() {
+                wires[wire_id].watch &= !WATCH_OBSERVE;
+            }
+            !ids.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::{CircuitBuilder, Voltage};
+    use crate::clocks::*;
+
+    #[test]
+    fn clock_edges() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_wire();
+        builder.add_component(Clock::new(), &[], &[clk]);
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+
+        let edges = Rc::new(RefCell::new(Vec::new()));
+        let edges2 = edges.clone();
+        let observer = circuit.add_observer(&[clk], move |change| {
+            edges2.borrow_mut().push((change.tick, change.old, change.new));
+        });
+        for _ in 0..3 {
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+        }
+        circuit.remove_observer(observer);
+        circuit.tick();
+        assert!(circuit.propagate(10).is_stable());
+
+        assert_eq!(*edges.borrow(), [
+            (1, Voltage::Low, Voltage::High),
+            (2, Voltage::High, Voltage::Low),
+        ]);
+    }
+}
@@ -0,0 +1,262 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::Component;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PinCount {
+    Exact(usize),
+    AtLeast(usize),
+    // `fixed + per_bit*n` pins, where `n` is the bit width of the component.
+    // With a `per_bit` of zero this is the same as `Exact(fixed)`.
+    PerBit { fixed: usize, per_bit: usize },
+}
+
+impl PinCount {
+    // Returns the bit width implied by `count` pins, if there is one
+    fn solve(self, count: usize) -> Result<Option<usize>, ()> {
+        match self {
+            PinCount::Exact(n) if count == n => Ok(None),
+            PinCount::AtLeast(n) if count >= n => Ok(None),
+            PinCount::PerBit { fixed, per_bit: 0 } if count == fixed => Ok(None),
+            PinCount::PerBit { fixed, per_bit } if count >= fixed && (count - fixed).checked_rem(per_bit) == Some(0) => {
+                Ok(Some((count - fixed) / per_bit))
+            },
+            _ => Err(()),
+        }
+    }
+    pub fn matches(self, count: usize) -> bool {
+        self.solve(count).is_ok()
+    }
+}
+
+impl From<usize> for PinCount {
+    fn from(count: usize) -> Self {
+        PinCount::Exact(count)
+    }
+}
+
+impl fmt::Display for PinCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PinCount::Exact(n) | PinCount::PerBit { fixed: n, per_bit: 0 } => write!(f, "{}", n),
+            PinCount::AtLeast(n) => write!(f, "at least {}", n),
+            PinCount::PerBit { fixed, per_bit } => {
+                if per_bit != 1 {
+                    write!(f, "{}", per_bit)?;
+                }
+                f.write_str("n")?;
+                if fixed != 0 {
+                    write!(f, "+{}", fixed)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+// The number of input and output pins a component expects to be connected to
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Arity {
+    pub inputs: PinCount,
+    pub outputs: PinCount,
+}
+
+impl Arity {
+    pub fn new<I: Into<PinCount>, O: Into<PinCount>>(inputs: I, outputs: O) -> Self {
+        Arity {
+            inputs: inputs.into(),
+            outputs: outputs.into(),
+        }
+    }
+    // Returns the bit width implied by the pin counts, if there is one
+    fn solve(&self, num_inputs: usize, num_outputs: usize) -> Result<Option<usize>, ()> {
+        match (self.inputs.solve(num_inputs)?, self.outputs.solve(num_outputs)?) {
+            (Some(a), Some(b)) if a != b => Err(()),
+            (a, b) => Ok(a.or(b)),
+        }
+    }
+    pub fn matches(&self, num_inputs: usize, num_outputs: usize) -> bool {
+        self.solve(num_inputs, num_outputs).is_ok()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PinWidth {
+    Fixed(usize),
+    // A multiple of the bit width of the component
+    Bits(usize),
+    // Whatever pins are left over
+    AtLeast(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PinGroup {
+    pub name: Cow<'static, str>,
+    pub width: PinWidth,
+    // Output pins which may float or only weakly drive their wire,
+    // and so can share a wire with other drivers
+    pub three_state: bool,
+}
+
+impl PinGroup {
+    pub fn new<N: Into<Cow<'static, str>>>(name: N, width: PinWidth) -> Self {
+        PinGroup { name: name.into(), width, three_state: false }
+    }
+    pub fn single<N: Into<Cow<'static, str>>>(name: N) -> Self {
+        Self::new(name, PinWidth::Fixed(1))
+    }
+    pub fn with_three_state(mut self, three_state: bool) -> Self {
+        self.three_state = three_state;
+        self
+    }
+}
+
+fn pin_count(groups: &[PinGroup]) -> PinCount {
+    let mut fixed = 0;
+    let mut per_bit = 0;
+    let mut variadic = false;
+    for group in groups {
+        match group.width {
+            PinWidth::Fixed(n) => fixed += n,
+            PinWidth::Bits(n) => per_bit += n,
+            PinWidth::AtLeast(n) => {
+                fixed += n;
+                variadic = true;
+            },
+        }
+    }
+    if variadic {
+        PinCount::AtLeast(fixed)
+    } else if per_bit > 0 {
+        PinCount::PerBit { fixed, per_bit }
+    } else {
+        PinCount::Exact(fixed)
+    }
+}
+
+// The group each pin belongs to, and its index within the group if the group is indexed
+fn pin_groups(groups: &[PinGroup], bits: usize, count: usize) -> Vec<Option<(&PinGroup, Option<usize>)>> {
+    let fixed: usize = groups.iter().map(|group| match group.width {
+        PinWidth::Fixed(n) | PinWidth::AtLeast(n) => n,
+        PinWidth::Bits(n) => n*bits,
+    }).sum();
+    let mut result = Vec::with_capacity(count);
+    for group in groups {
+        let (width, indexed) = match group.width {
+            PinWidth::Fixed(n) => (n, n != 1),
+            PinWidth::Bits(n) => (n*bits, true),
+            PinWidth::AtLeast(n) => (n + count.saturating_sub(fixed), true),
+        };
+        for i in 0..width {
+            result.push(Some((group, if indexed { Some(i) } else { None })));
+        }
+    }
+    result.resize(count, None);
+    result
+}
+
+fn pin_names(groups: &[PinGroup], bits: usize, count: usize) -> Vec<String> {
+    pin_groups(groups, bits, count).into_iter().enumerate().map(|(i, pin)| match pin {
+        Some((group, Some(index))) => format!("{}[{}]", group.name, index),
+        Some((group, None)) => group.name.to_string(),
+        None => format!("#{}", i),
+    }).collect()
+}
+
+// Describes the pins a component expects to be connected to
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Description {
+    pub name: Cow<'static, str>,
+    pub inputs: Vec<PinGroup>,
+    pub outputs: Vec<PinGroup>,
+    // Settings which distinguish this component from others of the same type
+    pub params: Vec<(Cow<'static, str>, String)>,
+}
+
+impl Description {
+    pub fn new<N: Into<Cow<'static, str>>>(name: N, inputs: Vec<PinGroup>, outputs: Vec<PinGroup>) -> Self {
+        Description { name: name.into(), inputs, outputs, params: Vec::new() }
+    }
+    pub fn with_param<N: Into<Cow<'static, str>>, V: fmt::Display>(mut self, name: N, value: V) -> Self {
+        self.params.push((name.into(), value.to_string()));
+        self
+    }
+    // The name followed by any parameters, eg. `Multiplexer(select_bits=2)`
+    pub fn label(&self) -> String {
+        if self.params.is_empty() {
+            return self.name.to_string();
+        }
+        let params: Vec<String> = self.params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        format!("{}({})", self.name, params.join(", "))
+    }
+    pub fn arity(&self) -> Arity {
+        Arity {
+            inputs: pin_count(&self.inputs),
+            outputs: pin_count(&self.outputs),
+        }
+    }
+    // Bit width implied by the given pin counts, or zero if it cannot be determined
+    fn bits(&self, num_inputs: usize, num_outputs: usize) -> usize {
+        self.arity().solve(num_inputs, num_outputs).ok().and_then(|bits| bits).unwrap_or(0)
+    }
+    pub fn input_names(&self, num_inputs: usize, num_outputs: usize) -> Vec<String> {
+        pin_names(&self.inputs, self.bits(num_inputs, num_outputs), num_inputs)
+    }
+    pub fn output_names(&self, num_inputs: usize, num_outputs: usize) -> Vec<String> {
+        pin_names(&self.outputs, self.bits(num_inputs, num_outputs), num_outputs)
+    }
+    pub fn three_state_outputs(&self, num_inputs: usize, num_outputs: usize) -> Vec<bool> {
+        pin_groups(&self.outputs, self.bits(num_inputs, num_outputs), num_outputs).into_iter()
+            .map(|pin| matches!(pin, Some((group, _)) if group.three_state))
+            .collect()
+    }
+}
+
+// Pin names for a component, falling back to `in[i]` and `out[i]` if it does not describe itself
+pub(crate) fn pin_labels(component: &dyn Component, num_inputs: usize, num_outputs: usize) -> (Vec<String>, Vec<String>) {
+    let description = component.describe().unwrap_or_else(|| Description::new("", vec![
+        PinGroup::new("in", PinWidth::AtLeast(0)),
+    ], vec![
+        PinGroup::new("out", PinWidth::AtLeast(0)),
+    ]));
+    (description.input_names(num_inputs, num_outputs), description.output_names(num_inputs, num_outputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adder() -> Description {
+        Description::new("Adder", vec![
+            PinGroup::new("a", PinWidth::Bits(1)),
+            PinGroup::new("b", PinWidth::Bits(1)),
+            PinGroup::single("cin"),
+        ], vec![
+            PinGroup::new("sum", PinWidth::Bits(1)),
+            PinGroup::single("cout"),
+        ])
+    }
+
+    #[test]
+    fn arity() {
+        let arity = adder().arity();
+        assert_eq!(arity.inputs.to_string(), "2n+1");
+        assert_eq!(arity.outputs.to_string(), "n+1");
+        assert!(arity.matches(9, 5));
+        assert!(!arity.matches(9, 6));
+        assert!(!arity.matches(8, 5));
+
+        let fixed = PinCount::PerBit { fixed: 2, per_bit: 0 };
+        assert_eq!(fixed.to_string(), "2");
+        assert!(fixed.matches(2));
+        assert!(!fixed.matches(3));
+    }
+
+    #[test]
+    fn names() {
+        let description = adder();
+        assert_eq!(description.input_names(5, 3), ["a[0]", "a[1]", "b[0]", "b[1]", "cin"]);
+        assert_eq!(description.output_names(5, 3), ["sum[0]", "sum[1]", "cout"]);
+    }
+}
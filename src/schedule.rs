@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::Circuit;
+use crate::circuit::ComponentWrapper;
+
+// Tick number used for components which do not need to be ticked
+pub(crate) const NEVER: u64 = !0;
+
+// The ticks at which components have asked to be woken up, soonest first.
+// Entries are left behind when a component is rescheduled, and are skipped
+// if they no longer match the component's `next_tick`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WakeList {
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl WakeList {
+    // Asks the component when it next needs to be ticked, starting from `tick`
+    pub(crate) fn schedule(&mut self, component_id: usize, component: &mut ComponentWrapper, tick: u64) {
+        let next_tick = component.component_impl.next_tick(tick).unwrap_or(NEVER);
+        debug_assert!(next_tick >= tick, "Component scheduled a tick in the past");
+        if next_tick != component.next_tick {
+            component.next_tick = next_tick;
+            if next_tick != NEVER {
+                self.heap.push(Reverse((next_tick, component_id)));
+            }
+        }
+    }
+    // Removes the next component which is due to be ticked at or before `tick`
+    pub(crate) fn pop_due(&mut self, components: &mut [ComponentWrapper], tick: u64) -> Option<usize> {
+        while let Some(&Reverse((next_tick, component_id))) = self.heap.peek() {
+            if next_tick > tick {
+                break;
+            }
+            self.heap.pop();
+            let component = &mut components[component_id];
+            if component.next_tick == next_tick {
+                component.next_tick = NEVER;
+                return Some(component_id);
+            }
+        }
+        None
+    }
+    // The next tick at which any component is due, discarding stale entries
+    fn next_due(&mut self, components: &[ComponentWrapper]) -> Option<u64> {
+        while let Some(&Reverse((next_tick, component_id))) = self.heap.peek() {
+            if components[component_id].next_tick == next_tick {
+                return Some(next_tick);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+    fn clear(&mut self) {
+        self.heap.clear();
+    }
+}
+
+impl Circuit {
+    // Components which were modified by the host must be asked for their next tick again
+    pub(crate) fn reschedule(&mut self, component_id: usize) {
+        if !self.rescheduled.contains(&component_id) {
+            self.rescheduled.push(component_id);
+        }
+    }
+    pub(crate) fn flush_rescheduled(&mut self) {
+        for component_id in std::mem::take(&mut self.rescheduled) {
+            self.wake_list.schedule(component_id, &mut self.components[component_id], self.tick_count);
+        }
+    }
+    // Skips straight past any ticks in which no component is scheduled, and then
+    // ticks the circuit. Returns the tick which was run, or `None` if no component
+    // will ever be ticked again. The circuit should be stable beforehand, as
+    // outstanding changes may cause components to be rescheduled.
+    pub fn advance_to_next_event(&mut self) -> Option<u64> {
+        if !self.rescheduled.is_empty() {
+            self.flush_rescheduled();
+        }
+        let next_tick = self.wake_list.next_due(&self.components)?;
+        if next_tick > self.tick_count {
+            if self.history.is_some() {
+                self.record_skip(next_tick);
+            }
+            self.tick_count = next_tick;
+        }
+        self.tick();
+        Some(next_tick)
+    }
+    // Rebuilds the wake list from scratch, after the state of every component has changed
+    pub(crate) fn schedule_all(&mut self) {
+        self.wake_list.clear();
+        self.rescheduled.clear();
+        for (component_id, component) in self.components.iter_mut().enumerate() {
+            component.next_tick = NEVER;
+            self.wake_list.schedule(component_id, component, self.tick_count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NEVER;
+    use crate::{CircuitBuilder, Component, ComponentInterface, Voltage};
+    use crate::clocks::*;
+    use crate::gates::*;
+
+    #[derive(Debug, Clone, Default)]
+    struct Ticker {
+        ticks: Vec<u64>,
+    }
+
+    impl Component for Ticker {
+        fn update(&mut self, _interface: &mut ComponentInterface) {}
+        fn tick(&mut self, tick: u64) -> bool {
+            self.ticks.push(tick);
+            false
+        }
+        fn next_tick(&self, tick: u64) -> Option<u64> {
+            Some(tick + (4 - tick % 4) % 4)
+        }
+    }
+
+    // Only overrides `tick`, so is ticked every tick by default
+    #[derive(Debug, Clone, Default)]
+    struct Counter {
+        count: u64,
+    }
+
+    impl Component for Counter {
+        fn update(&mut self, _interface: &mut ComponentInterface) {}
+        fn tick(&mut self, _tick: u64) -> bool {
+            self.count += 1;
+            false
+        }
+    }
+
+    #[test]
+    fn skipped_ticks() {
+        let mut builder = CircuitBuilder::new();
+        let enable = builder.add_input_wire();
+        let clk = builder.add_wire();
+        let nclk = builder.add_wire();
+        let mut clock = ControlledClock::new();
+        clock.set_ticks(2, 2);
+        let clock = builder.add_component(clock, &[enable], &[clk]);
+        let not = builder.add_component(NotGate::new(), &[clk], &[nclk]);
+        let ticker = builder.add_component(Ticker::default(), &[], &[]);
+        let counter = builder.add_component(Counter::default(), &[], &[]);
+        let mut circuit = builder.build();
+
+        let enabled = |tick: u64| !(6..11).contains(&tick);
+        let mut phase = 0;
+        for tick in 0..20 {
+            circuit.set_input(enable, enabled(tick).into());
+            assert!(circuit.propagate(10).is_stable());
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+            if !enabled(tick) {
+                phase = (phase + 3) % 4;
+            }
+            assert_eq!(circuit.wire(clk).voltage, ((tick + phase) % 4 >= 2).into(), "tick {}", tick);
+        }
+        assert_eq!(circuit.component_ref::<Ticker>(ticker).ticks, vec![0, 4, 8, 12, 16]);
+        assert_eq!(circuit.component_ref::<Counter>(counter).count, 20);
+        assert_eq!(circuit.wire(nclk).voltage, Voltage::Low);
+
+        // Gates are never ticked, and the clock is only woken on its edges
+        assert_eq!(circuit.components[not.0].next_tick, NEVER);
+        assert_eq!(circuit.components[clock.0].next_tick, 21);
+    }
+
+    #[test]
+    fn advance_to_next_event() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_wire();
+        let mut clock = Clock::new();
+        clock.set_ticks(1000, 1000);
+        builder.add_component(clock, &[], &[clk]);
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        circuit.record_history(1, 1 << 20);
+
+        for &edge in &[1000, 2000, 3000] {
+            assert_eq!(circuit.advance_to_next_event(), Some(edge));
+            assert_eq!(circuit.tick_count(), edge + 1);
+            assert!(circuit.propagate(10).is_stable());
+        }
+        assert_eq!(circuit.wire(clk).voltage, Voltage::High);
+
+        // Skipped ticks can still be rewound to
+        circuit.rewind_to(1500).unwrap();
+        assert_eq!((circuit.tick_count(), circuit.wire(clk).voltage), (1500, Voltage::High));
+        circuit.tick();
+        assert_eq!(circuit.advance_to_next_event(), Some(2000));
+
+        // Nothing left to wake up
+        let mut circuit = CircuitBuilder::new().build();
+        assert_eq!(circuit.advance_to_next_event(), None);
+        assert_eq!(circuit.tick_count(), 0);
+    }
+}
@@ -0,0 +1,245 @@
+use std::convert::TryInto;
+use std::mem;
+
+use crate::{Circuit, Voltage, VoltageInput};
+use crate::circuit::{NULL_INDEX, TAIL_INDEX};
+
+// Serializes the internal state of a component into a snapshot
+#[derive(Debug)]
+pub struct StateWriter<'a> {
+    data: &'a mut Vec<u8>,
+}
+
+impl<'a> StateWriter<'a> {
+    pub(crate) fn new(data: &'a mut Vec<u8>) -> Self {
+        StateWriter { data }
+    }
+    pub fn write_bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn write_voltage(&mut self, value: Voltage) {
+        self.data.push(value as u8);
+    }
+    pub fn write_input(&mut self, value: VoltageInput) {
+        self.data.push(value.voltage as u8 | (value.resistor as u8) << 2);
+    }
+}
+
+// Reads back the state written by `StateWriter`, in the same order
+#[derive(Debug)]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        StateReader { data }
+    }
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        assert!(self.data.len() >= len, "Snapshot does not match this circuit");
+        let (result, rest) = self.data.split_at(len);
+        self.data = rest;
+        result
+    }
+    pub fn read_bool(&mut self) -> bool {
+        self.take(1)[0] != 0
+    }
+    pub fn read_u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take(4).try_into().unwrap())
+    }
+    pub fn read_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+    pub fn read_voltage(&mut self) -> Voltage {
+        self.take(1)[0].into()
+    }
+    pub fn read_input(&mut self) -> VoltageInput {
+        let value = self.take(1)[0];
+        VoltageInput {
+            voltage: (value & 3).into(),
+            resistor: value & 4 != 0,
+        }
+    }
+}
+
+// The mutable state of a circuit, excluding its topology. Snapshots can only
+// be restored into the circuit they were taken from, or a clone of it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Snapshot {
+    tick_count: u64,
+    iteration_count: usize,
+    voltages: Vec<Voltage>,
+    wire_inputs: Vec<VoltageInput>,
+    pending_wires: Vec<usize>,
+    pending_component_sets: Vec<usize>,
+    dirty_components: Vec<usize>,
+    component_state: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+    pub fn iteration_count(&self) -> usize {
+        self.iteration_count
+    }
+    // Approximate memory usage in bytes
+    pub(crate) fn size(&self) -> usize {
+        mem::size_of::<Snapshot>()
+            + self.voltages.len()*mem::size_of::<Voltage>()
+            + self.wire_inputs.len()*mem::size_of::<VoltageInput>()
+            + (self.pending_wires.len() + self.pending_component_sets.len() + self.dirty_components.len())*mem::size_of::<usize>()
+            + self.component_state.len()
+    }
+}
+
+fn collect_list(first: usize, mut next: impl FnMut(usize) -> usize) -> Vec<usize> {
+    let mut result = Vec::new();
+    let mut index = first;
+    while index != TAIL_INDEX {
+        result.push(index);
+        index = next(index);
+    }
+    result
+}
+
+impl Circuit {
+    pub fn snapshot(&self) -> Snapshot {
+        let mut component_state = Vec::new();
+        let mut writer = StateWriter::new(&mut component_state);
+        for component in &self.components {
+            component.component_impl.save_state(&mut writer);
+        }
+        Snapshot {
+            tick_count: self.tick_count,
+            iteration_count: self.iteration_count,
+            voltages: self.wires.iter().map(|wire| wire.voltage).collect(),
+            wire_inputs: self.wires.iter().flat_map(|wire| wire.inputs.iter().cloned()).collect(),
+            pending_wires: self.pending_wire_ids().collect(),
+            pending_component_sets: collect_list(self.first_component_set, |index| self.component_sets[index].next),
+            dirty_components: self.dirty_components.clone(),
+            component_state,
+        }
+    }
+    // Wire changes caused by restoring a snapshot are not traced or observed
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        assert_eq!(snapshot.voltages.len(), self.wires.len(), "Snapshot does not match this circuit");
+
+        self.tick_count = snapshot.tick_count;
+        self.iteration_count = snapshot.iteration_count;
+
+        let mut inputs = snapshot.wire_inputs.iter();
+        for (wire, &voltage) in self.wires.iter_mut().zip(&snapshot.voltages) {
+            wire.voltage = voltage;
+            wire.next = NULL_INDEX;
+            for input in &mut wire.inputs {
+                *input = *inputs.next().expect("Snapshot does not match this circuit");
+            }
+        }
+        self.first_wire = TAIL_INDEX;
+        for &wire_id in snapshot.pending_wires.iter().rev() {
+            self.wires[wire_id].next = self.first_wire;
+            self.first_wire = wire_id;
+        }
+
+        for component_set in &mut self.component_sets {
+            component_set.next = NULL_INDEX;
+        }
+        self.first_component_set = TAIL_INDEX;
+        for &index in snapshot.pending_component_sets.iter().rev() {
+            self.component_sets[index].next = self.first_component_set;
+            self.first_component_set = index;
+        }
+
+        self.dirty_components.clone_from(&snapshot.dirty_components);
+
+        let mut reader = StateReader::new(&snapshot.component_state);
+        for component in &mut self.components {
+            component.iteration = NULL_INDEX;
+            component.component_impl.load_state(&mut reader);
+        }
+        self.schedule_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CircuitBuilder, Voltage};
+    use crate::clocks::*;
+    use crate::gates::*;
+    use crate::plexers::*;
+
+    #[test]
+    fn restore_clock() {
+        let mut builder = CircuitBuilder::new();
+        let enable = builder.add_wire();
+        let clk = builder.add_wire();
+        let nclk = builder.add_wire();
+        let enable_source = builder.add_component(Constant::new(Voltage::High.into()), &[], &[enable]);
+        builder.add_component(ControlledClock::new(), &[enable], &[clk]);
+        builder.add_component(NotGate::default(), &[clk], &[nclk]);
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+
+        let start = circuit.snapshot();
+        let mut states = Vec::new();
+        for i in 0..6 {
+            circuit.component_mut::<Constant>(enable_source).set((i % 4 != 1).into());
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+            states.push((circuit.snapshot(), circuit.wire(nclk)));
+        }
+        assert_ne!(states[0].0, states[2].0);
+
+        circuit.restore(&start);
+        assert_eq!(circuit.snapshot(), start);
+        for (i, expected) in states.iter().enumerate() {
+            circuit.component_mut::<Constant>(enable_source).set((i % 4 != 1).into());
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+            assert_eq!(&(circuit.snapshot(), circuit.wire(nclk)), expected);
+        }
+    }
+
+    #[test]
+    fn restore_configuration() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_wire();
+        let out = builder.add_bus(2);
+        let clock = builder.add_component(Clock::new(), &[], &[clk]);
+        let demux = builder.add_component(Demultiplexer::new(1), &[clk, clk], &out);
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+
+        let start = circuit.snapshot();
+        let mut states = Vec::new();
+        for _ in 0..4 {
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+            states.push(circuit.snapshot());
+        }
+
+        // Configuration changed after the snapshot is undone by restoring it
+        circuit.restore(&start);
+        circuit.modify_component(clock, |clock: &mut Clock| { clock.set_ticks(3, 2).set_phase(1); });
+        circuit.modify_component(demux, |demux: &mut Demultiplexer| { demux.set_three_state(true); });
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(out[1]).voltage, Voltage::Floating);
+
+        circuit.restore(&start);
+        assert_eq!(circuit.snapshot(), start);
+        assert_eq!(circuit.component_ref::<Clock>(clock).ticks_low(), 1);
+        assert!(!circuit.component_ref::<Demultiplexer>(demux).three_state());
+        for expected in &states {
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+            assert_eq!(&circuit.snapshot(), expected);
+        }
+    }
+}
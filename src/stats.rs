@@ -0,0 +1,82 @@
+use crate::{Circuit, RunError, RunStop, StopReason};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TickStats {
+    pub tick: u64,
+    // Iterations taken to settle after the tick
+    pub iterations: usize,
+    pub components_updated: u64,
+    pub wires_changed: u64,
+}
+
+// Returned by `Circuit::run`, which stops early if a breakpoint fires
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunSummary {
+    pub stop: RunStop,
+    pub stats: Vec<TickStats>,
+}
+
+impl Circuit {
+    // Finishes propagating any outstanding changes, then ticks and propagates the
+    // circuit `ticks` times. Stops early if a breakpoint fires, and fails at the first
+    // tick which doesn't settle within `max_iterations_per_tick` iterations.
+    pub fn run(&mut self, ticks: u64, max_iterations_per_tick: usize) -> Result<RunSummary, RunError> {
+        let mut stats = Vec::new();
+        let mut totals = (self.components_updated, self.wires_changed);
+        let stop = self.run_ticks(|_| false, ticks, max_iterations_per_tick, |circuit, ticks, result| {
+            if ticks > 0 {
+                stats.push(TickStats {
+                    tick: circuit.tick_count,
+                    iterations: result.iterations,
+                    components_updated: circuit.components_updated - totals.0,
+                    wires_changed: circuit.wires_changed - totals.1,
+                });
+            }
+            totals = (circuit.components_updated, circuit.wires_changed);
+        });
+        match stop.reason {
+            StopReason::Unstable(result) => Err(RunError { tick: stop.tick, result: Box::new(result), stats }),
+            _ => Ok(RunSummary { stop, stats }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Breakpoint, CircuitBuilder};
+    use crate::clocks::*;
+    use crate::gates::*;
+
+    #[test]
+    fn run() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_wire();
+        let nclk = builder.add_wire();
+        let mut clock = Clock::new();
+        clock.set_ticks(1, 2);
+        builder.add_component(clock, &[], &[clk]);
+        builder.add_component(NotGate::new(), &[clk], &[nclk]);
+        let mut circuit = builder.build();
+
+        let summary = circuit.run(3, 10).unwrap();
+        assert_eq!(summary.stop.reason, StopReason::MaxTicks);
+        assert_eq!(summary.stats, vec![
+            TickStats { tick: 1, iterations: 0, components_updated: 0, wires_changed: 0 },
+            TickStats { tick: 2, iterations: 2, components_updated: 2, wires_changed: 2 },
+            TickStats { tick: 3, iterations: 0, components_updated: 0, wires_changed: 0 },
+        ]);
+
+        let edge = circuit.add_breakpoint(Breakpoint::RisingEdge(nclk));
+        let summary = circuit.run(3, 10).unwrap();
+        assert_eq!((summary.stop.reason, summary.stop.tick), (StopReason::Breakpoint(edge), 3));
+        assert!(summary.stats.is_empty());
+        circuit.remove_breakpoint(edge);
+
+        // The next run finishes propagating the tick which was stopped first
+        let summary = circuit.run(1, 10).unwrap();
+        assert_eq!(summary.stats, vec![
+            TickStats { tick: 5, iterations: 2, components_updated: 2, wires_changed: 2 },
+        ]);
+    }
+}
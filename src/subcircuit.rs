@@ -0,0 +1,279 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use smallvec::SmallVec;
+
+use crate::{BusRef, WireRef, CircuitBuilder, BuildError};
+use crate::circuit::NULL_INDEX;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PortDirection {
+    Input,
+    Output,
+}
+
+impl fmt::Display for PortDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            PortDirection::Input => "input",
+            PortDirection::Output => "output",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Port {
+    name: String,
+    bus: BusRef,
+}
+
+impl Port {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn bus(&self) -> &BusRef {
+        &self.bus
+    }
+    pub fn width(&self) -> usize {
+        self.bus.width()
+    }
+}
+
+// A reusable block of components which can be instantiated into a `CircuitBuilder`
+#[derive(Clone, Debug, Default)]
+pub struct SubcircuitDef {
+    builder: CircuitBuilder,
+    inputs: Vec<Port>,
+    outputs: Vec<Port>,
+}
+
+impl SubcircuitDef {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    fn add_port(&mut self, name: &str, width: usize) -> Port {
+        let bus = self.builder.add_bus(width);
+        self.builder.name_bus(&bus, name);
+        Port { name: name.into(), bus }
+    }
+    pub fn add_input(&mut self, name: &str, width: usize) -> BusRef {
+        let port = self.add_port(name, width);
+        let result = port.bus.clone();
+        self.inputs.push(port);
+        result
+    }
+    pub fn add_output(&mut self, name: &str, width: usize) -> BusRef {
+        let port = self.add_port(name, width);
+        let result = port.bus.clone();
+        self.outputs.push(port);
+        result
+    }
+    pub fn inputs(&self) -> &[Port] {
+        &self.inputs
+    }
+    pub fn outputs(&self) -> &[Port] {
+        &self.outputs
+    }
+}
+
+impl Deref for SubcircuitDef {
+    type Target = CircuitBuilder;
+    fn deref(&self) -> &CircuitBuilder {
+        &self.builder
+    }
+}
+
+impl DerefMut for SubcircuitDef {
+    fn deref_mut(&mut self) -> &mut CircuitBuilder {
+        &mut self.builder
+    }
+}
+
+// Unconnected output ports are left as internal wires
+fn map_ports(wire_map: &mut [usize], ports: &[Port], wires: &[&[WireRef]]) {
+    for (port, &wires) in ports.iter().zip(wires) {
+        for (inner, outer) in port.bus.iter().zip(wires) {
+            if *outer != WireRef::NONE {
+                wire_map[inner.0] = outer.0;
+            }
+        }
+    }
+}
+
+impl CircuitBuilder {
+    fn check_ports(
+        &self,
+        name: &str,
+        inner: &CircuitBuilder,
+        ports: &[Port],
+        wires: &[&[WireRef]],
+        direction: PortDirection,
+    ) -> Result<(), BuildError> {
+        if ports.len() != wires.len() {
+            return Err(BuildError::PortCountMismatch {
+                subcircuit: name.into(),
+                direction,
+                expected: ports.len(),
+                actual: wires.len(),
+            });
+        }
+        for (port, &wires) in ports.iter().zip(wires) {
+            if port.width() != wires.len() {
+                return Err(BuildError::WidthMismatch {
+                    port: format!("{}.{}", name, port.name),
+                    expected: port.width(),
+                    actual: wires.len(),
+                });
+            }
+            for (pin, (wire, inner_wire)) in wires.iter().zip(port.bus.iter()).enumerate() {
+                if *wire == WireRef::NONE {
+                    if direction == PortDirection::Input {
+                        return Err(BuildError::NoneInput {
+                            component: format!("{}.{}", name, port.name),
+                            pin,
+                        });
+                    }
+                } else if wire.0 >= self.wires.len() {
+                    return Err(BuildError::UnknownWire(*wire));
+                } else if inner.wires[inner_wire.0].external {
+                    // Its value would otherwise be lost along with the external flag
+                    return Err(BuildError::InputPortConnected(format!("{}.{}", name, port.name)));
+                }
+            }
+        }
+        Ok(())
+    }
+    // Copies the contents of `def` into this builder. Wires and components
+    // named within the subcircuit can be looked up as `name.inner_name`.
+    pub fn add_subcircuit(
+        &mut self,
+        name: &str,
+        def: &SubcircuitDef,
+        inputs: &[&[WireRef]],
+        outputs: &[&[WireRef]],
+    ) -> &mut Self {
+        self.try_add_subcircuit(name, def, inputs, outputs).unwrap_or_else(|e| panic!("{}", e))
+    }
+    pub fn try_add_subcircuit(
+        &mut self,
+        name: &str,
+        def: &SubcircuitDef,
+        inputs: &[&[WireRef]],
+        outputs: &[&[WireRef]],
+    ) -> Result<&mut Self, BuildError> {
+        let inner = &def.builder;
+        self.check_ports(name, inner, &def.inputs, inputs, PortDirection::Input)?;
+        self.check_ports(name, inner, &def.outputs, outputs, PortDirection::Output)?;
+        if let Some(conflict) = self.names.merge_conflict(&inner.names, name) {
+            return Err(BuildError::DuplicateName(conflict));
+        }
+
+        // Port wires are replaced by the wires they are connected to
+        let mut wire_map = vec![NULL_INDEX; inner.wires.len()];
+        map_ports(&mut wire_map, &def.inputs, inputs);
+        map_ports(&mut wire_map, &def.outputs, outputs);
+        for (i, wire_id) in wire_map.iter_mut().enumerate() {
+            if *wire_id == NULL_INDEX {
+                *wire_id = if inner.wires[i].external { self.add_input_wire() } else { self.add_wire() }.0;
+            }
+        }
+
+        let first_component = self.components.len();
+        self.names.add_instance(name, first_component..(first_component + inner.components.len()));
+
+        let map_wire = |wire_id: usize| WireRef(if wire_id == NULL_INDEX { NULL_INDEX } else { wire_map[wire_id] });
+        for component in &inner.components {
+            let inputs: SmallVec<[WireRef; 4]> = component.inputs.iter().map(|&wire_id| map_wire(wire_id)).collect();
+            let outputs: SmallVec<[WireRef; 2]> = component.outputs.iter().map(|pin| map_wire(pin.wire_id)).collect();
+            self.add_boxed_component(component.component_impl.clone_box(), &inputs, &outputs);
+        }
+
+        self.names.merge(&inner.names, name, &wire_map, first_component);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Voltage;
+    use crate::gates::*;
+
+    fn half_adder() -> SubcircuitDef {
+        let mut def = SubcircuitDef::new();
+        let a = def.add_input("a", 1);
+        let b = def.add_input("b", 1);
+        let sum = def.add_output("sum", 1);
+        let carry = def.add_output("carry", 1);
+        let xor = def.add_component(XorGate::default(), &[a[0], b[0]], &sum);
+        def.add_component(AndGate::default(), &[a[0], b[0]], &carry);
+        def.name_component(xor, "xor");
+        def
+    }
+
+    #[test]
+    fn nested_subcircuits() {
+        // Two bit incrementer built from two half adders
+        let ha = half_adder();
+        let mut inc = SubcircuitDef::new();
+        let x = inc.add_input("x", 2);
+        let y = inc.add_output("y", 3);
+        let one = inc.add_wire();
+        let carry = inc.add_wire();
+        inc.add_component(Constant::new(Voltage::High.into()), &[], &[one]);
+        inc.add_subcircuit("lo", &ha, &[&x.slice(0..1), &[one]], &[&y.slice(0..1), &[carry]]);
+        inc.add_subcircuit("hi", &ha, &[&x.slice(1..2), &[carry]], &[&y.slice(1..2), &y.slice(2..3)]);
+
+        let mut builder = CircuitBuilder::new();
+        let input = builder.add_bus(2);
+        let output = builder.add_bus(3);
+        builder.add_component(Constant::new(Voltage::High.into()), &[], &[input[0]]);
+        builder.add_component(Constant::new(Voltage::High.into()), &[], &[input[1]]);
+        builder.add_subcircuit("inc", &inc, &[&input], &[&output]);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus(&output).to_u64(), Some(4));
+        assert_eq!(circuit.bus_by_name("inc.y"), Some(output.clone()));
+        assert_eq!(circuit.wire_by_name("inc.y[2]"), Some(output[2]));
+        assert_eq!(circuit.wire_by_name("inc.hi.sum"), Some(output[1]));
+        assert_eq!(circuit.wire(circuit.wire_by_name("inc.lo.carry").unwrap()).voltage, Voltage::High);
+
+        let xor = circuit.component_by_name("inc.hi.xor").unwrap();
+        assert_eq!(circuit.scope_of(xor), Some("inc.hi"));
+        assert_eq!(circuit.component_by_name("inc.xor"), None);
+    }
+
+    #[test]
+    fn name_conflicts() {
+        let ha = half_adder();
+        let mut builder = CircuitBuilder::new();
+        let io = builder.add_bus(4);
+        builder.add_named_wire("b.carry");
+        builder.add_subcircuit("a", &ha, &[&io.slice(0..1), &io.slice(1..2)], &[&io.slice(2..3), &io.slice(3..4)]);
+        let (wires, components) = (builder.wires.len(), builder.components.len());
+
+        // Conflicts are found before the builder is changed
+        for &(name, conflict) in &[("a", "a"), ("b", "b.carry")] {
+            assert_eq!(
+                builder.try_add_subcircuit(name, &ha, &[&[io[0]], &[io[1]]], &[&[WireRef::NONE], &[WireRef::NONE]]).err(),
+                Some(BuildError::DuplicateName(conflict.into()))
+            );
+            assert_eq!((builder.wires.len(), builder.components.len()), (wires, components));
+        }
+        assert!(builder.try_add_subcircuit("c", &ha, &[&[io[0]], &[io[1]]], &[&[WireRef::NONE], &[WireRef::NONE]]).is_ok());
+    }
+
+    #[test]
+    fn unconnected_bits() {
+        let mut ha = half_adder();
+        let a = ha.inputs()[0].bus().bit(0);
+        ha.name_bus(&[a, WireRef::NONE], "padded");
+
+        let mut builder = CircuitBuilder::new();
+        let io = builder.add_bus(4);
+        builder.add_subcircuit("ha", &ha, &[&io.slice(0..1), &io.slice(1..2)], &[&io.slice(2..3), &io.slice(3..4)]);
+        let circuit = builder.build();
+        assert_eq!(circuit.bus_by_name("ha.padded"), Some(BusRef::from(&[io[0], WireRef::NONE][..])));
+    }
+}
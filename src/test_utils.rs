@@ -2,7 +2,7 @@ use crate::{AnyComponent, Voltage, CircuitBuilder};
 use crate::gates::Constant;
 
 fn simulate_component_internal(
-    component: Box<AnyComponent>,
+    component: Box<dyn AnyComponent>,
     inputs: &[(usize, u64, u64)],
     outputs: &[usize],
 ) -> Vec<(u64, u64)> {
@@ -39,7 +39,7 @@ fn simulate_component_internal(
     builder.add_boxed_component(component, &input_wires, &output_wires);
 
     let mut circuit = builder.build();
-    assert!(circuit.propagate(10));
+    assert!(circuit.propagate(10).is_stable());
 
     // Decode result
     let mut result = Vec::new();
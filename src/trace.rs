@@ -0,0 +1,44 @@
+use std::vec::Drain;
+
+use crate::{Circuit, Voltage, WireRef};
+use crate::circuit::WATCH_TRACE;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WireChange {
+    pub wire: WireRef,
+    pub old: Voltage,
+    pub new: Voltage,
+    // The tick which caused the change, changes before the first tick belong to tick 0
+    pub tick: u64,
+    pub iteration: usize,
+}
+
+impl Circuit {
+    // `tick_count` has already moved on by the time the changes from a tick are propagated
+    pub(crate) fn change_tick(&self) -> u64 {
+        self.tick_count.saturating_sub(1)
+    }
+    // Changes to traced wires are buffered until they are drained, so make
+    // sure to call `drain_changes` regularly whilst tracing is enabled.
+    pub fn trace_wire(&mut self, wref: WireRef, enabled: bool) -> &mut Self {
+        let wire = &mut self.wires[wref.0];
+        if enabled {
+            wire.watch |= WATCH_TRACE;
+        } else {
+            wire.watch &= !WATCH_TRACE;
+        }
+        self
+    }
+    pub fn trace_all(&mut self, enabled: bool) -> &mut Self {
+        for i in 0..self.wires.len() {
+            self.trace_wire(WireRef(i), enabled);
+        }
+        self
+    }
+    pub fn is_traced(&self, wref: WireRef) -> bool {
+        self.wires[wref.0].watch & WATCH_TRACE != 0
+    }
+    pub fn drain_changes(&mut self) -> Drain<'_, WireChange> {
+        self.changes.drain(..)
+    }
+}
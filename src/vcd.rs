@@ -0,0 +1,314 @@
+// Value Change Dump output, for viewing waveforms in eg. GTKWave
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::num::NonZeroU64;
+
+use smallvec::SmallVec;
+
+use crate::{Circuit, Voltage, WireChange, WireRef};
+
+fn voltage_char(voltage: Voltage) -> char {
+    match voltage {
+        Voltage::Low => '0',
+        Voltage::High => '1',
+        Voltage::Floating => 'z',
+        Voltage::Error => 'x',
+    }
+}
+
+// Identifiers are made of the printable ASCII characters
+fn identifier(mut index: usize) -> String {
+    let mut result = String::new();
+    loop {
+        result.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return result;
+        }
+        index -= 1;
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Signal {
+    name: String,
+    wires: SmallVec<[WireRef; 1]>,
+    values: SmallVec<[Voltage; 1]>,
+    dirty: bool,
+}
+
+#[derive(Debug)]
+pub struct VcdTracer<W: Write> {
+    out: W,
+    timescale: String,
+    sub_steps: Option<NonZeroU64>,
+    signals: Vec<Signal>,
+    wire_signals: HashMap<usize, SmallVec<[(usize, usize); 1]>>,
+    dirty: Vec<usize>,
+    // Wires which were traced by this writer, rather than already being traced
+    traced: Vec<WireRef>,
+    time: u64,
+    last_written: Option<u64>,
+}
+
+impl<W: Write> VcdTracer<W> {
+    pub fn new(out: W) -> Self {
+        VcdTracer {
+            out,
+            timescale: "1ns".into(),
+            sub_steps: None,
+            signals: Vec::new(),
+            wire_signals: HashMap::new(),
+            dirty: Vec::new(),
+            traced: Vec::new(),
+            time: 0,
+            last_written: None,
+        }
+    }
+    pub fn set_timescale(&mut self, timescale: &str) -> &mut Self {
+        self.timescale = timescale.into();
+        self
+    }
+    // When set, each tick is split into `sub_steps` time steps, one per iteration
+    pub fn set_sub_steps(&mut self, sub_steps: Option<NonZeroU64>) -> &mut Self {
+        self.sub_steps = sub_steps;
+        self
+    }
+    pub fn add_bus(&mut self, name: &str, bus: &[WireRef]) -> &mut Self {
+        let index = self.signals.len();
+        for (bit, wire) in bus.iter().enumerate() {
+            self.wire_signals.entry(wire.0).or_default().push((index, bit));
+        }
+        self.signals.push(Signal {
+            name: name.into(),
+            wires: bus.iter().cloned().collect(),
+            values: SmallVec::from_elem(Voltage::Floating, bus.len()),
+            dirty: false,
+        });
+        self
+    }
+    pub fn add_wire(&mut self, name: &str, wire: WireRef) -> &mut Self {
+        self.add_bus(name, &[wire])
+    }
+    // Adds every wire in the circuit, grouping wires into buses where they were named that way
+    pub fn add_all(&mut self, circuit: &Circuit) -> &mut Self {
+        let names = &circuit.names;
+        let mut added = vec![false; circuit.wires.len()];
+        for (name, bus) in names.buses() {
+            let is_primary = bus.width() > 1 && bus.iter().enumerate().all(|(i, &wire)| {
+                names.primary_name(wire) == Some((name, Some(i)))
+            });
+            if is_primary {
+                for wire in bus {
+                    added[wire.0] = true;
+                }
+                self.add_bus(name, bus);
+            }
+        }
+        for (i, &added) in added.iter().enumerate() {
+            if !added {
+                let name = names.name_of(WireRef(i)).unwrap_or_else(|| format!("w{}", i));
+                self.add_wire(&name, WireRef(i));
+            }
+        }
+        self
+    }
+    fn time_of(&self, tick: u64, iteration: usize) -> u64 {
+        match self.sub_steps {
+            Some(sub_steps) => tick*sub_steps.get() + cmp::min(iteration as u64, sub_steps.get() - 1),
+            None => tick,
+        }
+    }
+    fn write_value(&mut self, index: usize) -> io::Result<()> {
+        let signal = &self.signals[index];
+        let id = identifier(index);
+        if signal.values.len() == 1 {
+            writeln!(self.out, "{}{}", voltage_char(signal.values[0]), id)
+        } else {
+            let value: String = signal.values.iter().rev().cloned().map(voltage_char).collect();
+            writeln!(self.out, "b{} {}", value, id)
+        }
+    }
+    fn write_header(&mut self) -> io::Result<()> {
+        writeln!(self.out, "$version tenorite $end")?;
+        writeln!(self.out, "$timescale {} $end", self.timescale)?;
+        writeln!(self.out, "$scope module top $end")?;
+
+        // Hierarchical names are split into nested scopes
+        let mut order: Vec<usize> = (0..self.signals.len()).collect();
+        order.sort_by(|&a, &b| self.signals[a].name.cmp(&self.signals[b].name));
+        let mut scopes: Vec<&str> = Vec::new();
+        for index in order {
+            let signal = &self.signals[index];
+            let mut path: Vec<&str> = signal.name.split('.').collect();
+            let leaf = path.pop().unwrap();
+            let common = scopes.iter().zip(&path).take_while(|(a, b)| a == b).count();
+            for _ in common..scopes.len() {
+                writeln!(self.out, "$upscope $end")?;
+            }
+            scopes.truncate(common);
+            for &scope in &path[common..] {
+                writeln!(self.out, "$scope module {} $end", scope)?;
+                scopes.push(scope);
+            }
+            let width = signal.values.len();
+            let (leaf, range) = match leaf.find('[') {
+                Some(open) => (&leaf[..open], leaf[open..].to_string()),
+                None if width > 1 => (leaf, format!("[{}:0]", width - 1)),
+                None => (leaf, String::new()),
+            };
+            if range.is_empty() {
+                writeln!(self.out, "$var wire {} {} {} $end", width, identifier(index), leaf)?;
+            } else {
+                writeln!(self.out, "$var wire {} {} {} {} $end", width, identifier(index), leaf, range)?;
+            }
+        }
+        for _ in 0..scopes.len() {
+            writeln!(self.out, "$upscope $end")?;
+        }
+
+        writeln!(self.out, "$upscope $end")?;
+        writeln!(self.out, "$enddefinitions $end")
+    }
+    // Writes the header and initial values, and enables tracing for the wires being recorded
+    pub fn start(&mut self, circuit: &mut Circuit) -> io::Result<()> {
+        self.write_header()?;
+        self.time = self.time_of(circuit.change_tick(), circuit.iteration_count());
+        self.last_written = Some(self.time);
+        writeln!(self.out, "#{}", self.time)?;
+        writeln!(self.out, "$dumpvars")?;
+        for index in 0..self.signals.len() {
+            let signal = &mut self.signals[index];
+            for (value, &wire) in signal.values.iter_mut().zip(&signal.wires) {
+                *value = circuit.wire(wire).voltage;
+                if !circuit.is_traced(wire) {
+                    circuit.trace_wire(wire, true);
+                    self.traced.push(wire);
+                }
+            }
+            self.write_value(index)?;
+        }
+        writeln!(self.out, "$end")
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+        if self.last_written != Some(self.time) {
+            writeln!(self.out, "#{}", self.time)?;
+            self.last_written = Some(self.time);
+        }
+        let mut dirty = std::mem::take(&mut self.dirty);
+        for &index in &dirty {
+            self.signals[index].dirty = false;
+            self.write_value(index)?;
+        }
+        dirty.clear();
+        self.dirty = dirty;
+        Ok(())
+    }
+    fn apply(&mut self, change: WireChange) -> io::Result<()> {
+        let time = self.time_of(change.tick, change.iteration);
+        if time != self.time {
+            self.flush()?;
+            self.time = time;
+        }
+        if let Some(entries) = self.wire_signals.get(&change.wire.0) {
+            for &(index, bit) in entries {
+                let signal = &mut self.signals[index];
+                signal.values[bit] = change.new;
+                if !signal.dirty {
+                    signal.dirty = true;
+                    self.dirty.push(index);
+                }
+            }
+        }
+        Ok(())
+    }
+    // Records all changes since the last call, should be called after each `propagate`
+    pub fn record(&mut self, circuit: &mut Circuit) -> io::Result<()> {
+        for change in circuit.drain_changes() {
+            self.apply(change)?;
+        }
+        Ok(())
+    }
+    // Records any outstanding changes and disables tracing for the wires traced by `start`
+    pub fn finish(mut self, circuit: &mut Circuit) -> io::Result<W> {
+        self.record(circuit)?;
+        self.flush()?;
+        for &wire in &self.traced {
+            circuit.trace_wire(wire, false);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CircuitBuilder;
+    use crate::gates::*;
+    use crate::clocks::*;
+
+    #[test]
+    fn clock_waveform() {
+        let mut builder = CircuitBuilder::new();
+        let clk = builder.add_named_wire("clk");
+        let data = builder.add_named_bus("cpu.data", 2);
+        builder.add_component(Clock::new(), &[], &[clk]);
+        builder.add_component(Buffer::default(), &[clk], &[data[0]]);
+        builder.add_component(NotGate::default(), &[data[0]], &[data[1]]);
+
+        let mut circuit = builder.build();
+        assert!(circuit.propagate(10).is_stable());
+
+        // Wires which were already traced are left that way
+        circuit.trace_wire(clk, true);
+        let mut vcd = VcdTracer::new(Vec::new());
+        vcd.add_all(&circuit);
+        vcd.start(&mut circuit).unwrap();
+        for _ in 0..3 {
+            circuit.tick();
+            assert!(circuit.propagate(10).is_stable());
+            vcd.record(&mut circuit).unwrap();
+        }
+        let output = String::from_utf8(vcd.finish(&mut circuit).unwrap()).unwrap();
+        assert!(circuit.is_traced(clk));
+        assert!(!circuit.is_traced(data[0]));
+
+        assert_eq!(output, "\
+$version tenorite $end
+$timescale 1ns $end
+$scope module top $end
+$var wire 1 \" clk $end
+$scope module cpu $end
+$var wire 2 ! data [1:0] $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+b10 !
+0\"
+$end
+#1
+1\"
+b01 !
+#2
+0\"
+b10 !
+");
+    }
+
+    #[test]
+    fn sub_steps() {
+        let mut vcd = VcdTracer::new(Vec::new());
+        assert_eq!(vcd.time_of(2, 3), 2);
+        // Iterations past the last sub-step share its time
+        vcd.set_sub_steps(NonZeroU64::new(4));
+        assert_eq!(vcd.time_of(2, 1), 9);
+        assert_eq!(vcd.time_of(2, 10), 11);
+    }
+}
@@ -0,0 +1,1080 @@
+// Reading gate-level structural Verilog netlists
+use std::collections::{HashMap, HashSet};
+
+use crate::{AnyComponent, BusRef, CircuitBuilder, ComponentRef, ParseError, PortDirection, SubcircuitDef, Voltage, WireRef};
+use crate::gates::*;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+// Longer symbols come first, so that they are matched in preference to their prefixes
+const SYMBOLS: &[&str] = &["~^", "^~", "(", ")", "[", "]", "{", "}", ",", ";", ":", ".", "=", "~", "&", "|", "^", "#"];
+
+const PRIMITIVES: &[&str] = &["and", "or", "xor", "nand", "nor", "xnor", "not", "buf", "bufif1", "notif1"];
+
+const UNSUPPORTED: &[&str] = &[
+    "inout", "reg", "integer", "parameter", "localparam", "always", "initial",
+    "function", "task", "generate", "bufif0", "notif0",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let length = if c == '\n' {
+            line += 1;
+            1
+        } else if c.is_whitespace() {
+            c.len_utf8()
+        } else if rest.starts_with("//") || c == '`' {
+            // Compiler directives such as `timescale are skipped along with comments
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            let end = rest.find("*/").ok_or_else(|| ParseError::new(line, "Unterminated comment"))?;
+            line += rest[..end].matches('\n').count();
+            end + 2
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$')).unwrap_or(rest.len());
+            tokens.push((line, Token::Ident(rest[..end].into())));
+            end
+        } else if c == '\\' {
+            // Escaped identifiers run until the next whitespace
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            tokens.push((line, Token::Ident(rest[1..end].into())));
+            end
+        } else if c.is_ascii_digit() || c == '\'' {
+            let mut end = rest.find(|c: char| !(c.is_ascii_digit() || c == '_')).unwrap_or(rest.len());
+            if rest[end..].starts_with('\'') {
+                end += 1;
+                end += rest[end..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '?')).unwrap_or(rest.len() - end);
+            }
+            tokens.push((line, Token::Number(rest[..end].into())));
+            end
+        } else if let Some(&symbol) = SYMBOLS.iter().find(|&&symbol| rest.starts_with(symbol)) {
+            tokens.push((line, Token::Symbol(symbol)));
+            symbol.len()
+        } else {
+            return Err(ParseError::new(line, format!("Unexpected character `{}`", c)));
+        };
+        rest = &rest[length..];
+    }
+    Ok(tokens)
+}
+
+// Parses a literal such as `8'hFF` into its width, if it has one, and its bits
+// from least to most significant
+fn parse_number(text: &str) -> Result<(Option<usize>, Vec<Voltage>), String> {
+    let invalid = || format!("Invalid number `{}`", text);
+    let (size, base, digits) = match text.find('\'') {
+        None => (None, 'd', text),
+        Some(quote) => {
+            let size = if quote == 0 {
+                None
+            } else {
+                Some(text[..quote].replace('_', "").parse::<usize>().map_err(|_| invalid())?)
+            };
+            let spec = text[quote + 1..].trim_start_matches(['s', 'S']);
+            let base = spec.chars().next().ok_or_else(invalid)?.to_ascii_lowercase();
+            (size, base, &spec[1..])
+        },
+    };
+    let digits: Vec<char> = digits.chars().filter(|&c| c != '_').collect();
+    if digits.is_empty() || size == Some(0) {
+        return Err(invalid());
+    }
+    let mut bits = Vec::new();
+    if base == 'd' {
+        let value: u64 = digits.iter().collect::<String>().parse().map_err(|_| invalid())?;
+        for i in 0..(64 - value.leading_zeros()).max(1) {
+            bits.push(((value >> i) & 1 == 1).into());
+        }
+    } else {
+        let bits_per_digit = match base {
+            'b' => 1,
+            'o' => 3,
+            'h' => 4,
+            _ => return Err(invalid()),
+        };
+        for c in digits.iter().rev() {
+            let digit = match c.to_ascii_lowercase() {
+                'x' => Err(Voltage::Error),
+                'z' | '?' => Err(Voltage::Floating),
+                c => Ok(c.to_digit(1 << bits_per_digit).ok_or_else(invalid)?),
+            };
+            for i in 0..bits_per_digit {
+                bits.push(match digit {
+                    Ok(digit) => ((digit >> i) & 1 == 1).into(),
+                    Err(voltage) => voltage,
+                });
+            }
+        }
+    }
+    if let Some(size) = size {
+        bits.resize(size, Voltage::Low);
+    }
+    Ok((size, bits))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    And,
+    Or,
+    Xor,
+    Xnor,
+}
+
+// Binary operators, from the lowest to the highest precedence
+const OPERATORS: &[&[(&str, Op)]] = &[
+    &[("|", Op::Or)],
+    &[("^", Op::Xor), ("~^", Op::Xnor), ("^~", Op::Xnor)],
+    &[("&", Op::And)],
+];
+
+#[derive(Clone, Debug)]
+enum Expr {
+    // A whole net, or a bit or part select
+    Net(String, Option<(i64, i64)>),
+    Number(Option<usize>, Vec<Voltage>),
+    // Parts are listed from the most significant, as written
+    Concat(Vec<Expr>),
+    Repeat(usize, Vec<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+struct NetDecl {
+    name: String,
+    range: Option<(i64, i64)>,
+    direction: Option<PortDirection>,
+}
+
+impl NetDecl {
+    fn width(&self) -> usize {
+        self.range.map(|(msb, lsb)| (msb - lsb).unsigned_abs() as usize + 1).unwrap_or(1)
+    }
+}
+
+enum Connections {
+    Positional(Vec<Option<Expr>>),
+    Named(Vec<(String, Option<Expr>)>),
+}
+
+enum Item {
+    Assign {
+        line: usize,
+        lhs: Expr,
+        rhs: Expr,
+    },
+    Primitive {
+        line: usize,
+        kind: String,
+        name: Option<String>,
+        terminals: Vec<Expr>,
+    },
+    Instance {
+        line: usize,
+        module: String,
+        name: String,
+        connections: Connections,
+    },
+}
+
+struct Module {
+    line: usize,
+    ports: Vec<String>,
+    nets: Vec<NetDecl>,
+    items: Vec<Item>,
+}
+
+impl Module {
+    fn net(&self, name: &str) -> Option<&NetDecl> {
+        self.nets.iter().find(|net| net.name == name)
+    }
+    // Port declarations may be followed by a net declaration for the same name
+    fn declare(&mut self, line: usize, name: String, range: Option<(i64, i64)>, direction: Option<PortDirection>) -> Result<(), ParseError> {
+        match self.nets.iter_mut().find(|net| net.name == name) {
+            Some(net) => {
+                if net.range != range {
+                    return Err(ParseError::new(line, format!("`{}` is declared with different widths", name)));
+                }
+                if direction.is_some() {
+                    if net.direction.is_some() {
+                        return Err(ParseError::new(line, format!("Port `{}` is declared twice", name)));
+                    }
+                    net.direction = direction;
+                }
+            },
+            None => self.nets.push(NetDecl { name, range, direction }),
+        }
+        Ok(())
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or_else(|| self.tokens.last()).map(|&(line, _)| line).unwrap_or(1)
+    }
+    fn error<M: Into<String>>(&self, message: M) -> ParseError {
+        ParseError::new(self.line(), message)
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+    fn is(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s == keyword)
+    }
+    fn eat(&mut self, symbol: &str) -> bool {
+        let result = self.is(symbol);
+        if result {
+            self.pos += 1;
+        }
+        result
+    }
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let result = self.is_keyword(keyword);
+        if result {
+            self.pos += 1;
+        }
+        result
+    }
+    fn expect(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected `{}`", symbol)))
+        }
+    }
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            },
+            _ => Err(self.error("Expected an identifier")),
+        }
+    }
+    fn integer(&mut self) -> Result<i64, ParseError> {
+        match self.peek() {
+            Some(Token::Number(text)) if !text.contains('\'') => {
+                let value = text.replace('_', "").parse().map_err(|_| self.error(format!("Invalid number `{}`", text)))?;
+                self.pos += 1;
+                Ok(value)
+            },
+            _ => Err(self.error("Expected an integer")),
+        }
+    }
+    fn range(&mut self) -> Result<Option<(i64, i64)>, ParseError> {
+        if !self.eat("[") {
+            return Ok(None);
+        }
+        let msb = self.integer()?;
+        let lsb = if self.eat(":") { self.integer()? } else { msb };
+        self.expect("]")?;
+        Ok(Some((msb, lsb)))
+    }
+    fn direction(&mut self) -> Option<PortDirection> {
+        if self.eat_keyword("input") {
+            Some(PortDirection::Input)
+        } else if self.eat_keyword("output") {
+            Some(PortDirection::Output)
+        } else {
+            None
+        }
+    }
+    // Delays have no meaning in this simulator, so are skipped
+    fn skip_delay(&mut self) -> Result<(), ParseError> {
+        if !self.eat("#") {
+            return Ok(());
+        }
+        if self.eat("(") {
+            let mut depth = 1;
+            while depth > 0 {
+                match self.peek() {
+                    Some(Token::Symbol("(")) => depth += 1,
+                    Some(Token::Symbol(")")) => depth -= 1,
+                    Some(_) => {},
+                    None => return Err(self.error("Expected `)`")),
+                }
+                self.pos += 1;
+            }
+            Ok(())
+        } else if let Some(Token::Number(_)) = self.peek() {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error("Expected a delay"))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.binary(0)
+    }
+    fn binary(&mut self, level: usize) -> Result<Expr, ParseError> {
+        if level == OPERATORS.len() {
+            return self.unary();
+        }
+        let mut result = self.binary(level + 1)?;
+        while let Some(&(_, op)) = OPERATORS[level].iter().find(|(symbol, _)| self.is(symbol)) {
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            result = Expr::Binary(op, Box::new(result), Box::new(right));
+        }
+        Ok(result)
+    }
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("~") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::Number(text)) => {
+                let (width, bits) = parse_number(&text).map_err(|message| self.error(message))?;
+                self.pos += 1;
+                Ok(Expr::Number(width, bits))
+            },
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                Ok(Expr::Net(name, self.range()?))
+            },
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let result = self.expr()?;
+                self.expect(")")?;
+                Ok(result)
+            },
+            Some(Token::Symbol("{")) => {
+                self.pos += 1;
+                let first = self.expr()?;
+                if self.is("{") {
+                    // Replication, such as `{4{a}}`
+                    let count = match first {
+                        Expr::Number(None, bits) => bits.iter().rev().fold(0, |count, &bit| count * 2 + (bit == Voltage::High) as usize),
+                        _ => return Err(self.error("Expected a replication count")),
+                    };
+                    if count == 0 {
+                        return Err(self.error("Replication count must be positive"));
+                    }
+                    self.pos += 1;
+                    let parts = self.expr_list()?;
+                    self.expect("}")?;
+                    self.expect("}")?;
+                    return Ok(Expr::Repeat(count, parts));
+                }
+                let mut parts = vec![first];
+                while self.eat(",") {
+                    parts.push(self.expr()?);
+                }
+                self.expect("}")?;
+                Ok(Expr::Concat(parts))
+            },
+            _ => Err(self.error("Expected an expression")),
+        }
+    }
+    fn expr_list(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut result = vec![self.expr()?];
+        while self.eat(",") {
+            result.push(self.expr()?);
+        }
+        Ok(result)
+    }
+
+    fn connections(&mut self) -> Result<Connections, ParseError> {
+        self.expect("(")?;
+        let result = if self.is(".") {
+            let mut named = Vec::new();
+            loop {
+                self.expect(".")?;
+                let port = self.ident()?;
+                self.expect("(")?;
+                let expr = if self.is(")") { None } else { Some(self.expr()?) };
+                self.expect(")")?;
+                named.push((port, expr));
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            Connections::Named(named)
+        } else if self.is(")") {
+            Connections::Positional(Vec::new())
+        } else {
+            let mut positional = Vec::new();
+            loop {
+                positional.push(if self.is(",") || self.is(")") { None } else { Some(self.expr()?) });
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            Connections::Positional(positional)
+        };
+        self.expect(")")?;
+        Ok(result)
+    }
+    fn instance_name(&mut self, names: &mut HashSet<String>, name: String) -> Result<String, ParseError> {
+        if !names.insert(name.clone()) {
+            return Err(self.error(format!("Duplicate instance name `{}`", name)));
+        }
+        if self.is("[") {
+            return Err(self.error("Arrays of instances are not supported"));
+        }
+        Ok(name)
+    }
+
+    fn module(&mut self) -> Result<(String, Module), ParseError> {
+        let line = self.line();
+        let name = self.ident()?;
+        let mut module = Module { line, ports: Vec::new(), nets: Vec::new(), items: Vec::new() };
+        if self.is("#") {
+            return Err(self.error("Parameters are not supported"));
+        }
+        if self.eat("(") && !self.eat(")") {
+            let mut declared: Option<(PortDirection, Option<(i64, i64)>)> = None;
+            loop {
+                // Ports declared in the header carry their direction on to the following ports
+                if let Some(direction) = self.direction() {
+                    self.eat_keyword("wire");
+                    declared = Some((direction, self.range()?));
+                }
+                let line = self.line();
+                let port = self.ident()?;
+                if module.ports.contains(&port) {
+                    return Err(ParseError::new(line, format!("Port `{}` is listed twice", port)));
+                }
+                if let Some((direction, range)) = declared {
+                    module.declare(line, port.clone(), range, Some(direction))?;
+                }
+                module.ports.push(port);
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect(")")?;
+        }
+        self.expect(";")?;
+
+        let mut instances = HashSet::new();
+        while !self.eat_keyword("endmodule") {
+            let line = self.line();
+            let keyword = match self.peek() {
+                Some(Token::Ident(keyword)) => keyword.clone(),
+                Some(_) => return Err(self.error("Expected a declaration or an instance")),
+                None => return Err(self.error("Expected `endmodule`")),
+            };
+            if let Some(direction) = self.direction() {
+                self.eat_keyword("wire");
+                let range = self.range()?;
+                for name in self.ident_list()? {
+                    module.declare(line, name, range, Some(direction))?;
+                }
+                self.expect(";")?;
+            } else if self.eat_keyword("wire") {
+                let range = self.range()?;
+                loop {
+                    let name = self.ident()?;
+                    module.declare(line, name.clone(), range, None)?;
+                    if self.eat("=") {
+                        module.items.push(Item::Assign { line, lhs: Expr::Net(name, None), rhs: self.expr()? });
+                    }
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect(";")?;
+            } else if self.eat_keyword("assign") {
+                loop {
+                    let lhs = self.expr()?;
+                    self.expect("=")?;
+                    module.items.push(Item::Assign { line, lhs, rhs: self.expr()? });
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect(";")?;
+            } else if PRIMITIVES.contains(&&keyword[..]) {
+                self.pos += 1;
+                self.skip_delay()?;
+                loop {
+                    let line = self.line();
+                    let name = match self.peek() {
+                        Some(Token::Ident(name)) => {
+                            let name = name.clone();
+                            self.pos += 1;
+                            Some(self.instance_name(&mut instances, name)?)
+                        },
+                        _ => None,
+                    };
+                    self.expect("(")?;
+                    let terminals = self.expr_list()?;
+                    self.expect(")")?;
+                    module.items.push(Item::Primitive { line, kind: keyword.clone(), name, terminals });
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect(";")?;
+            } else if UNSUPPORTED.contains(&&keyword[..]) || keyword == "module" {
+                return Err(self.error(format!("`{}` is not supported in structural netlists", keyword)));
+            } else {
+                self.pos += 1;
+                if self.is("#") {
+                    return Err(self.error("Parameters are not supported"));
+                }
+                loop {
+                    let line = self.line();
+                    let name = self.ident()?;
+                    let name = self.instance_name(&mut instances, name)?;
+                    let connections = self.connections()?;
+                    module.items.push(Item::Instance { line, module: keyword.clone(), name, connections });
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect(";")?;
+            }
+        }
+
+        for port in &module.ports {
+            if module.net(port).and_then(|net| net.direction).is_none() {
+                return Err(ParseError::new(module.line, format!("Port `{}` has no direction", port)));
+            }
+        }
+        Ok((name, module))
+    }
+    fn ident_list(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut result = vec![self.ident()?];
+        while self.eat(",") {
+            result.push(self.ident()?);
+        }
+        Ok(result)
+    }
+}
+
+fn parse(source: &str) -> Result<HashMap<String, Module>, ParseError> {
+    let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+    let mut modules = HashMap::new();
+    while parser.peek().is_some() {
+        if !parser.eat_keyword("module") {
+            return Err(parser.error("Expected `module`"));
+        }
+        let line = parser.line();
+        let (name, module) = parser.module()?;
+        if modules.insert(name.clone(), module).is_some() {
+            return Err(ParseError::new(line, format!("Module `{}` is defined twice", name)));
+        }
+    }
+    Ok(modules)
+}
+
+// Adds the gates for a primitive with the given inputs, returning the one driving `output`
+fn primitive(builder: &mut CircuitBuilder, kind: &str, inputs: &[WireRef], output: WireRef) -> ComponentRef {
+    let component: Box<dyn AnyComponent> = match (kind, inputs.len()) {
+        ("buf", _) | ("and", 1) | ("or", 1) | ("xor", 1) => Box::new(Buffer::new()),
+        ("not", _) | ("nand", 1) | ("nor", 1) | ("xnor", 1) => Box::new(NotGate::new()),
+        ("and", 2) => Box::new(AndGate::new()),
+        ("and", _) => Box::new(NaryAndGate::new()),
+        ("or", 2) => Box::new(OrGate::new()),
+        ("or", _) => Box::new(NaryOrGate::new()),
+        ("xor", 2) => Box::new(XorGate::new()),
+        ("xor", _) => Box::new(ParityGate::new()),
+        ("nand", 2) => Box::new(NandGate::new()),
+        ("nor", 2) => Box::new(NorGate::new()),
+        ("xnor", 2) => Box::new(XnorGate::new()),
+        // Wider inverted gates are the plain gate followed by a `NotGate`
+        ("nand", _) | ("nor", _) | ("xnor", _) => {
+            let plain = match kind {
+                "nand" => "and",
+                "nor" => "or",
+                _ => "xor",
+            };
+            let wire = builder.add_wire();
+            primitive(builder, plain, inputs, wire);
+            return builder.add_component(NotGate::new(), &[wire], &[output]);
+        },
+        ("bufif1", _) => Box::new(ControlledBuffer::new()),
+        ("notif1", _) => Box::new(ControlledInverter::new()),
+        _ => unreachable!("Unknown primitive `{}`", kind),
+    };
+    builder.add_boxed_component(component, inputs, &[output])
+}
+
+fn operator(op: Op) -> Box<dyn AnyComponent> {
+    match op {
+        Op::And => Box::new(AndGate::new()),
+        Op::Or => Box::new(OrGate::new()),
+        Op::Xor => Box::new(XorGate::new()),
+        Op::Xnor => Box::new(XnorGate::new()),
+    }
+}
+
+struct Net {
+    bus: BusRef,
+    msb: i64,
+    lsb: i64,
+}
+
+impl Net {
+    // Position within the bus, which is ordered from the least significant bit
+    fn position(&self, index: i64) -> Option<usize> {
+        let position = if self.msb >= self.lsb { index - self.lsb } else { self.lsb - index };
+        if position >= 0 && (position as usize) < self.bus.width() {
+            Some(position as usize)
+        } else {
+            None
+        }
+    }
+}
+
+// The nets of one module, as it is being built
+struct Scope<'b> {
+    builder: &'b mut CircuitBuilder,
+    nets: HashMap<String, Net>,
+    constants: HashMap<Voltage, WireRef>,
+}
+
+impl<'b> Scope<'b> {
+    fn select(&mut self, name: &str, select: Option<(i64, i64)>) -> Result<Vec<WireRef>, String> {
+        if !self.nets.contains_key(name) {
+            if select.is_some() {
+                return Err(format!("Unknown net `{}`", name));
+            }
+            // Undeclared nets are implicitly one bit wide
+            let wire = self.builder.add_named_wire(name);
+            self.nets.insert(name.into(), Net { bus: wire.into(), msb: 0, lsb: 0 });
+        }
+        let net = &self.nets[name];
+        match select {
+            None => Ok(net.bus.to_vec()),
+            Some((msb, lsb)) => {
+                let out_of_range = || format!("Index out of range for `{}`", name);
+                let high = net.position(msb).ok_or_else(out_of_range)?;
+                let low = net.position(lsb).ok_or_else(out_of_range)?;
+                if high < low {
+                    return Err(format!("Part select of `{}` is reversed", name));
+                }
+                Ok(net.bus[low..=high].to_vec())
+            },
+        }
+    }
+    fn lvalue(&mut self, expr: &Expr) -> Result<Vec<WireRef>, String> {
+        match expr {
+            Expr::Net(name, select) => self.select(name, *select),
+            Expr::Concat(parts) => {
+                let mut result = Vec::new();
+                for part in parts.iter().rev() {
+                    result.extend(self.lvalue(part)?);
+                }
+                Ok(result)
+            },
+            _ => Err("Expected a net".into()),
+        }
+    }
+    // Unsized numbers take their width from the context they are used in
+    fn width(&self, expr: &Expr, hint: Option<usize>) -> Result<usize, String> {
+        match expr {
+            Expr::Net(name, None) => Ok(self.nets.get(name).map(|net| net.bus.width()).unwrap_or(1)),
+            Expr::Net(name, Some((msb, lsb))) => {
+                let net = self.nets.get(name).ok_or_else(|| format!("Unknown net `{}`", name))?;
+                match (net.position(*msb), net.position(*lsb)) {
+                    (Some(high), Some(low)) if high >= low => Ok(high - low + 1),
+                    _ => Err(format!("Invalid select of `{}`", name)),
+                }
+            },
+            Expr::Number(Some(width), _) => Ok(*width),
+            Expr::Number(None, _) => hint.ok_or_else(|| "Unsized numbers are not allowed here".into()),
+            Expr::Not(inner) => self.width(inner, hint),
+            Expr::Binary(_, a, b) => {
+                let is_unsized = |expr: &Expr| matches!(expr, Expr::Number(None, _));
+                let (a_width, b_width) = match (is_unsized(a), is_unsized(b)) {
+                    (true, false) => {
+                        let width = self.width(b, hint)?;
+                        (width, width)
+                    },
+                    (false, true) => {
+                        let width = self.width(a, hint)?;
+                        (width, width)
+                    },
+                    _ => (self.width(a, hint)?, self.width(b, hint)?),
+                };
+                if a_width != b_width {
+                    return Err(format!("Operands have different widths, {} and {}", a_width, b_width));
+                }
+                Ok(a_width)
+            },
+            Expr::Concat(parts) => parts.iter().map(|part| self.width(part, None)).sum(),
+            Expr::Repeat(count, parts) => Ok(count * self.width(&Expr::Concat(parts.clone()), None)?),
+        }
+    }
+    fn constant(&mut self, voltage: Voltage) -> WireRef {
+        let builder = &mut self.builder;
+        *self.constants.entry(voltage).or_insert_with(|| {
+            let wire = builder.add_wire();
+            builder.add_component(Constant::new(voltage.into()), &[], &[wire]);
+            wire
+        })
+    }
+    fn outputs(&mut self, width: usize, target: Option<&[WireRef]>) -> Vec<WireRef> {
+        match target {
+            Some(target) => target.to_vec(),
+            None => (0..width).map(|_| self.builder.add_wire()).collect(),
+        }
+    }
+    // Builds the gates for an expression of the given width. When a target is given,
+    // the result drives those wires, otherwise new wires are created as needed.
+    fn eval(&mut self, expr: &Expr, width: usize, target: Option<&[WireRef]>) -> Result<Vec<WireRef>, String> {
+        let result = match expr {
+            Expr::Net(name, select) => self.select(name, *select)?,
+            Expr::Number(_, bits) => {
+                let mut bits = bits.clone();
+                bits.resize(width, Voltage::Low);
+                if let Some(target) = target {
+                    for (&bit, &wire) in bits.iter().zip(target) {
+                        self.builder.add_component(Constant::new(bit.into()), &[], &[wire]);
+                    }
+                    return Ok(target.to_vec());
+                }
+                bits.into_iter().map(|bit| self.constant(bit)).collect()
+            },
+            Expr::Not(inner) => {
+                let inputs = self.eval(inner, width, None)?;
+                let outputs = self.outputs(width, target);
+                for (&input, &output) in inputs.iter().zip(&outputs) {
+                    self.builder.add_component(NotGate::new(), &[input], &[output]);
+                }
+                return Ok(outputs);
+            },
+            Expr::Binary(op, a, b) => {
+                let a = self.eval(a, width, None)?;
+                let b = self.eval(b, width, None)?;
+                let outputs = self.outputs(width, target);
+                for ((&a, &b), &output) in a.iter().zip(&b).zip(&outputs) {
+                    self.builder.add_boxed_component(operator(*op), &[a, b], &[output]);
+                }
+                return Ok(outputs);
+            },
+            Expr::Concat(parts) => {
+                let mut result = Vec::new();
+                for part in parts.iter().rev() {
+                    let part_width = self.width(part, None)?;
+                    let part_target = target.map(|target| &target[result.len()..result.len() + part_width]);
+                    result.extend(self.eval(part, part_width, part_target)?);
+                }
+                if target.is_some() {
+                    return Ok(result);
+                }
+                result
+            },
+            Expr::Repeat(count, parts) => {
+                let inner = self.eval(&Expr::Concat(parts.clone()), width / count, None)?;
+                (0..*count).flat_map(|_| inner.iter().cloned()).collect()
+            },
+        };
+        // Nets and constants are copied onto the target through buffers
+        if let Some(target) = target {
+            for (&input, &output) in result.iter().zip(target) {
+                self.builder.add_component(Buffer::new(), &[input], &[output]);
+            }
+            return Ok(target.to_vec());
+        }
+        Ok(result)
+    }
+}
+
+struct Elaborator<'a> {
+    modules: &'a HashMap<String, Module>,
+    defs: HashMap<String, SubcircuitDef>,
+    // Modules currently being built, to detect recursive instantiation
+    stack: Vec<String>,
+}
+
+impl<'a> Elaborator<'a> {
+    fn subcircuit(&mut self, name: &str, line: usize) -> Result<(), ParseError> {
+        if self.defs.contains_key(name) {
+            return Ok(());
+        }
+        let modules = self.modules;
+        let module = modules.get(name).ok_or_else(|| ParseError::new(line, format!("Unknown module `{}`", name)))?;
+        if self.stack.iter().any(|parent| parent == name) {
+            return Err(ParseError::new(line, format!("Module `{}` instantiates itself", name)));
+        }
+        self.stack.push(name.into());
+        let mut def = SubcircuitDef::new();
+        let mut ports = HashMap::new();
+        for port in &module.ports {
+            let net = module.net(port).unwrap();
+            let bus = match net.direction {
+                Some(PortDirection::Input) => def.add_input(port, net.width()),
+                _ => def.add_output(port, net.width()),
+            };
+            ports.insert(port.clone(), bus);
+        }
+        self.build(module, &mut def, ports)?;
+        self.stack.pop();
+        self.defs.insert(name.into(), def);
+        Ok(())
+    }
+    fn build(&mut self, module: &Module, builder: &mut CircuitBuilder, mut ports: HashMap<String, BusRef>) -> Result<(), ParseError> {
+        let mut scope = Scope {
+            nets: HashMap::new(),
+            constants: HashMap::new(),
+            builder,
+        };
+        for net in &module.nets {
+            let bus = ports.remove(&net.name).unwrap_or_else(|| scope.builder.add_named_bus(&net.name, net.width()));
+            let (msb, lsb) = net.range.unwrap_or((0, 0));
+            scope.nets.insert(net.name.clone(), Net { bus, msb, lsb });
+        }
+
+        for item in &module.items {
+            match item {
+                Item::Assign { line, lhs, rhs } => {
+                    let error = |message| ParseError::new(*line, message);
+                    let target = scope.lvalue(lhs).map_err(error)?;
+                    let width = scope.width(rhs, Some(target.len())).map_err(error)?;
+                    if width != target.len() {
+                        return Err(error(format!("Cannot assign {} bits to {} bits", width, target.len())));
+                    }
+                    scope.eval(rhs, width, Some(&target)).map_err(error)?;
+                },
+                Item::Primitive { line, kind, name, terminals } => {
+                    let error = |message| ParseError::new(*line, message);
+                    // Buffers and inverters may drive several outputs from their last terminal
+                    let num_outputs = if kind == "not" || kind == "buf" { terminals.len().saturating_sub(1) } else { 1 };
+                    let controlled = kind.ends_with("if1");
+                    if (controlled && terminals.len() != 3) || terminals.len() < 2 {
+                        return Err(error(format!("Wrong number of terminals for `{}`", kind)));
+                    }
+                    let mut wires = Vec::new();
+                    for (i, terminal) in terminals.iter().enumerate() {
+                        let bits = if i < num_outputs {
+                            scope.lvalue(terminal)
+                        } else {
+                            scope.width(terminal, Some(1)).and_then(|width| scope.eval(terminal, width, None))
+                        }.map_err(error)?;
+                        if bits.len() != 1 {
+                            return Err(error("Primitive terminals must be one bit wide".into()));
+                        }
+                        wires.push(bits[0]);
+                    }
+                    let (outputs, inputs) = wires.split_at(num_outputs);
+                    for (i, &output) in outputs.iter().enumerate() {
+                        // Instances driving several outputs are named `name[i]`, which may
+                        // clash with an escaped identifier or with a net of a subcircuit
+                        let name = match name {
+                            Some(name) if outputs.len() == 1 => Some(name.clone()),
+                            Some(name) => Some(format!("{}[{}]", name, i)),
+                            None => None,
+                        };
+                        if let Some(name) = &name {
+                            if scope.builder.component_by_name(name).is_some() {
+                                return Err(error(format!("Duplicate instance name `{}`", name)));
+                            }
+                        }
+                        let component = primitive(scope.builder, kind, inputs, output);
+                        if let Some(name) = &name {
+                            scope.builder.name_component(component, name);
+                        }
+                    }
+                },
+                Item::Instance { line, module: module_name, name, connections } => {
+                    let error = |message| ParseError::new(*line, message);
+                    self.subcircuit(module_name, *line)?;
+                    let instantiated = &self.modules[module_name];
+                    let mut connected: Vec<Option<&Expr>> = vec![None; instantiated.ports.len()];
+                    match connections {
+                        Connections::Positional(exprs) => {
+                            if exprs.len() > connected.len() {
+                                return Err(error(format!("Module `{}` only has {} ports", module_name, connected.len())));
+                            }
+                            for (slot, expr) in connected.iter_mut().zip(exprs) {
+                                *slot = expr.as_ref();
+                            }
+                        },
+                        Connections::Named(exprs) => {
+                            for (port, expr) in exprs {
+                                let index = instantiated.ports.iter().position(|p| p == port)
+                                    .ok_or_else(|| error(format!("Module `{}` has no port `{}`", module_name, port)))?;
+                                connected[index] = expr.as_ref();
+                            }
+                        },
+                    }
+
+                    let mut inputs = Vec::new();
+                    let mut outputs = Vec::new();
+                    for (port, expr) in instantiated.ports.iter().zip(connected) {
+                        let net = instantiated.net(port).unwrap();
+                        let width = net.width();
+                        let wires = match (net.direction, expr) {
+                            (Some(PortDirection::Input), Some(expr)) => {
+                                let actual = scope.width(expr, Some(width)).map_err(error)?;
+                                if actual != width {
+                                    return Err(error(format!("Port `{}` has width {}, but is connected to {} bits", port, width, actual)));
+                                }
+                                scope.eval(expr, width, None).map_err(error)?
+                            },
+                            (Some(PortDirection::Input), None) => {
+                                return Err(error(format!("Input `{}` of `{}` is not connected", port, name)));
+                            },
+                            (_, Some(expr)) => scope.lvalue(expr).map_err(error)?,
+                            (_, None) => vec![WireRef::NONE; width],
+                        };
+                        if net.direction == Some(PortDirection::Input) {
+                            inputs.push(wires);
+                        } else {
+                            outputs.push(wires);
+                        }
+                    }
+                    let inputs: Vec<&[WireRef]> = inputs.iter().map(|wires| &wires[..]).collect();
+                    let outputs: Vec<&[WireRef]> = outputs.iter().map(|wires| &wires[..]).collect();
+                    scope.builder.try_add_subcircuit(name, &self.defs[module_name], &inputs, &outputs)
+                        .map_err(|e| error(e.to_string()))?;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+// Builds a circuit from the module called `top` in a gate-level Verilog netlist.
+// The ports of the top module become named input ports and named buses, and other
+// modules are built as subcircuits, so their nets are named `instance.net`. Gate
+// primitives and the bitwise operators in `assign` statements are mapped onto the
+// gates in `gates`, with nets assigned directly from another net or a constant
+// being driven through a `Buffer`. Operands must have the same width.
+pub fn read_verilog(source: &str, top: &str) -> Result<CircuitBuilder, ParseError> {
+    let modules = parse(source)?;
+    let module = modules.get(top).ok_or_else(|| ParseError::new(0, format!("No module called `{}`", top)))?;
+    let mut builder = CircuitBuilder::new();
+    let mut ports = HashMap::new();
+    for port in &module.ports {
+        let net = module.net(port).unwrap();
+        let bus = match net.direction {
+            Some(PortDirection::Input) => builder.add_named_input_bus(port, net.width()),
+            _ => builder.add_named_bus(port, net.width()),
+        };
+        ports.insert(port.clone(), bus);
+    }
+    let mut elaborator = Elaborator {
+        modules: &modules,
+        defs: HashMap::new(),
+        stack: vec![top.into()],
+    };
+    elaborator.build(module, &mut builder, ports)?;
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adder() {
+        let builder = read_verilog(r"
+`timescale 1ns / 1ps
+// Half adder built from primitives
+module half_adder(input a, b, output sum, carry);
+    xor x1(sum, a, b);
+    and #1 a1(carry, a, b);
+endmodule
+
+/* Two bit adder, with a three-state carry out */
+module adder(a, b, en, y, all, flags);
+    input [1:0] a, b;
+    input en;
+    output [2:0] y;
+    output all;
+    output [1:0] flags;
+    wire s1, c0, c1, c2, carry;
+    half_adder h0(.a(a[0]), .b(b[0]), .sum(y[0]), .carry(c0));
+    half_adder h1(a[1], b[1], s1, c1);
+    half_adder h2(.a(s1), .b(c0), .sum(y[1]), .carry(c2));
+    or (carry, c1, c2);
+    bufif1 (y[2], carry, en);
+    and all_gate(all, a[0], a[1], b[0], b[1]);
+    assign flags = {a[1] & ~b[1], 1'b1};
+endmodule
+", "adder").unwrap();
+        assert!(builder.lint().is_empty());
+        let a = builder.bus_by_name("a").unwrap();
+        let b = builder.bus_by_name("b").unwrap();
+        let en = builder.wire_by_name("en").unwrap();
+        let y = builder.bus_by_name("y").unwrap();
+        let all = builder.wire_by_name("all").unwrap();
+        let flags = builder.bus_by_name("flags").unwrap();
+        assert!(builder.wire_by_name("h1.sum").is_some());
+        let all_gate = builder.component_by_name("all_gate").unwrap();
+        let xor = builder.component_by_name("h2.x1").unwrap();
+
+        let mut circuit = builder.build();
+        circuit.component_ref::<NaryAndGate>(all_gate);
+        circuit.component_ref::<XorGate>(xor);
+        circuit.set_input_bus(&a, 3);
+        circuit.set_input_bus(&b, 2);
+        circuit.set_input(en, Voltage::High.into());
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.bus(&y).to_u64(), Some(5));
+        assert_eq!(circuit.wire(all).voltage, Voltage::Low);
+        assert_eq!(circuit.bus(&flags).to_u64(), Some(1));
+
+        circuit.set_input(en, Voltage::Low.into());
+        assert!(circuit.propagate(10).is_stable());
+        assert_eq!(circuit.wire(y[2]).voltage, Voltage::Floating);
+
+        assert_eq!(
+            read_verilog("module top(y);\n    output y;\n    full_adder fa(y);\nendmodule\n", "top").err().map(|e| e.to_string()),
+            Some("Line 3: Unknown module `full_adder`".into())
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let read = |source: &str| read_verilog(source, "top").err().map(|e| e.to_string());
+        assert_eq!(
+            read("module top(a, a);\n    input a;\nendmodule\n"),
+            Some("Line 1: Port `a` is listed twice".into())
+        );
+        assert_eq!(
+            read("module top(a, y, z, w);\n    input a;\n    output y, z, w;\n    not g2(y, z, a);\n    buf \\g2[0] (w, a);\nendmodule\n"),
+            Some("Line 5: Duplicate instance name `g2[0]`".into())
+        );
+        assert_eq!(
+            read("module inner(output x);\n    buf b(x, 1'b1);\nendmodule\n\nmodule top(y);\n    output y;\n    buf \\h.b (y, 1'b0);\n    inner h(y);\nendmodule\n"),
+            Some("Line 8: Name `h.b` is already in use".into())
+        );
+
+        // Wide inverted gates are built from the plain gate and a `NotGate`
+        let builder = read_verilog("\
+module top(a, y);
+    input [2:0] a;
+    output [2:0] y;
+    nand (y[0], a[0], a[1], a[2]);
+    nor (y[1], a[0], a[1], a[2]);
+    xnor (y[2], a[0], a[1], a[2]);
+endmodule
+", "top").unwrap();
+        let a = builder.bus_by_name("a").unwrap();
+        let y = builder.bus_by_name("y").unwrap();
+        let mut circuit = builder.build();
+        for value in 0..8u64 {
+            circuit.set_input_bus(&a, value);
+            assert!(circuit.propagate(10).is_stable());
+            let expected = (value != 7) as u64 | ((value == 0) as u64) << 1 | ((value.count_ones() % 2 == 0) as u64) << 2;
+            assert_eq!(circuit.bus(&y).to_u64(), Some(expected), "a: {}", value);
+        }
+    }
+}